@@ -0,0 +1,21 @@
+// Benchmarks the NTT, inverse NTT and base multiplication, run with cargo bench --bench arithmetic --features hazmat
+// Adding the portable feature times the portable code instead of the AVX2 backend
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use fips203_rust::hazmat::{NttPoly, Poly, ntt, ntt_inv};
+
+fn bench_arithmetic(c: &mut Criterion) {
+    let f = Poly::from_coefficients(&core::array::from_fn(|i| (i * 1337) as u16));
+    let g = Poly::from_coefficients(&core::array::from_fn(|i| (i * 2718 + 1) as u16));
+    let (f_ntt, g_ntt): (NttPoly, NttPoly) = (ntt(&f), ntt(&g));
+
+    let mut group = c.benchmark_group(if cfg!(feature = "portable") { "arithmetic-portable" } else { "arithmetic" });
+    group.bench_function(BenchmarkId::from_parameter("ntt"), |b| b.iter(|| ntt(black_box(&f))));
+    group.bench_function(BenchmarkId::from_parameter("ntt_inv"), |b| b.iter(|| ntt_inv(black_box(&f_ntt))));
+    group.bench_function(BenchmarkId::from_parameter("multiply_ntts"), |b| b.iter(|| black_box(&f_ntt) * black_box(&g_ntt)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_arithmetic);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+// Benchmarks the typed API for each parameter set, run with cargo bench
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use fips203_rust::{MlKem512, MlKem768, MlKem1024, ParameterSet, typed::MlKem, types::DecapsKeySeed};
+use rand_chacha::ChaCha20Rng;
+use rand_chacha::rand_core::SeedableRng;
+
+fn bench_params<P: ParameterSet>(c: &mut Criterion, name: &str) {
+    let kem = MlKem::<P>::new();
+    let seed = DecapsKeySeed::from([7u8; 64]);
+    let (ek, dk) = kem.keygen_from_seed(&seed);
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (_, ct) = kem.encaps_with_rng(&ek, &mut rng).unwrap();
+    let prepared_ek = kem.prepare_encaps_key(&ek);
+    let prepared_dk = kem.prepare_decaps_key(&dk);
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function(BenchmarkId::from_parameter("keygen"), |b| b.iter(|| kem.keygen_from_seed(&seed)));
+    group.bench_function(BenchmarkId::from_parameter("encaps"), |b| b.iter(|| kem.encaps_with_rng(&ek, &mut rng)));
+    group.bench_function(BenchmarkId::from_parameter("decaps"), |b| b.iter(|| kem.decaps(&dk, &ct)));
+    group.bench_function(BenchmarkId::from_parameter("encaps_prepared"), |b| b.iter(|| kem.encaps_prepared_with_rng(&prepared_ek, &mut rng)));
+    group.bench_function(BenchmarkId::from_parameter("decaps_prepared"), |b| b.iter(|| kem.decaps_prepared(&prepared_dk, &ct)));
+    group.finish();
+}
+
+fn bench_ml_kem(c: &mut Criterion) {
+    bench_params::<MlKem512>(c, "ML-KEM-512");
+    bench_params::<MlKem768>(c, "ML-KEM-768");
+    bench_params::<MlKem1024>(c, "ML-KEM-1024");
+}
+
+criterion_group!(benches, bench_ml_kem);
+criterion_main!(benches);
@@ -0,0 +1,117 @@
+/* FIPS 203 ML-KEM C API. Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#ifndef FIPS203_H
+#define FIPS203_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+// ML-KEM-512 (security category 1).
+#define FIPS203_ML_KEM_512 512
+
+// ML-KEM-768 (security category 3).
+#define FIPS203_ML_KEM_768 768
+
+// ML-KEM-1024 (security category 5).
+#define FIPS203_ML_KEM_1024 1024
+
+// Length of the shared secret for every parameter set.
+#define FIPS203_SHARED_SECRET_LEN 32
+
+// Length of the key generation seed `d || z` for every parameter set.
+#define FIPS203_SEED_LEN 64
+
+// The operation succeeded.
+#define FIPS203_OK 0
+
+// A required pointer was null.
+#define FIPS203_ERR_NULL_POINTER -1
+
+// The parameter set is not one of the `FIPS203_ML_KEM_*` values.
+#define FIPS203_ERR_INVALID_PARAMS -2
+
+// An output buffer does not have the length required by the parameter set.
+#define FIPS203_ERR_BUFFER_LENGTH -3
+
+// The encapsulation key failed the FIPS 203 §7.2 checks.
+#define FIPS203_ERR_INVALID_ENCAPS_KEY -4
+
+// The decapsulation key failed the FIPS 203 §7.3 checks.
+#define FIPS203_ERR_INVALID_DECAPS_KEY -5
+
+// The ciphertext does not have the length required by the parameter set.
+#define FIPS203_ERR_INVALID_CIPHERTEXT -6
+
+// The operating system random number generator failed.
+#define FIPS203_ERR_RANDOMNESS -7
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the encapsulation key length for the parameter set, or 0 if it is unknown.
+size_t fips203_encaps_key_len(uint32_t params);
+
+// Returns the decapsulation key length for the parameter set, or 0 if it is unknown.
+size_t fips203_decaps_key_len(uint32_t params);
+
+// Returns the ciphertext length for the parameter set, or 0 if it is unknown.
+size_t fips203_ciphertext_len(uint32_t params);
+
+// Returns a static description of the status code.
+const char *fips203_status_string(int status);
+
+// Generates a key pair with the operating system random number generator.
+//
+// # Safety
+//
+// `ek` must be valid for writes of `ek_len` bytes and `dk` for writes of `dk_len` bytes.
+int fips203_keygen(uint32_t params, uint8_t *ek, size_t ek_len, uint8_t *dk, size_t dk_len);
+
+// Deterministically expands the `FIPS203_SEED_LEN` byte seed `d || z` into a key pair.
+//
+// # Safety
+//
+// `seed` must be valid for reads of `FIPS203_SEED_LEN` bytes, `ek` for writes of `ek_len` bytes
+// and `dk` for writes of `dk_len` bytes.
+int fips203_keygen_from_seed(uint32_t params,
+                             const uint8_t *seed,
+                             uint8_t *ek,
+                             size_t ek_len,
+                             uint8_t *dk,
+                             size_t dk_len);
+
+// Encapsulates a fresh shared secret to the encapsulation key.
+//
+// # Safety
+//
+// `ek` must be valid for reads of `ek_len` bytes, `ct` for writes of `ct_len` bytes and
+// `shared_secret` for writes of `FIPS203_SHARED_SECRET_LEN` bytes.
+int fips203_encaps(uint32_t params,
+                   const uint8_t *ek,
+                   size_t ek_len,
+                   uint8_t *ct,
+                   size_t ct_len,
+                   uint8_t *shared_secret);
+
+// Decapsulates the shared secret from the ciphertext.
+//
+// A ciphertext of the right length always yields a shared secret, which is the implicit
+// rejection value if the ciphertext was not produced for this key.
+//
+// # Safety
+//
+// `dk` must be valid for reads of `dk_len` bytes, `ct` for reads of `ct_len` bytes and
+// `shared_secret` for writes of `FIPS203_SHARED_SECRET_LEN` bytes.
+int fips203_decaps(uint32_t params,
+                   const uint8_t *dk,
+                   size_t dk_len,
+                   const uint8_t *ct,
+                   size_t ct_len,
+                   uint8_t *shared_secret);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FIPS203_H */
@@ -0,0 +1,199 @@
+// Runs NIST ACVP ML-KEM vector sets against the FIPS 203 internal functions
+//
+// A vector set is a directory holding prompt.json and expectedResults.json, named and laid out as
+// in gen-val/json-files of the ACVP-Server repository. The samples in tests/data/acvp were produced
+// with OpenSSL 3.5, set ACVP_VECTORS_DIR to a json-files checkout to run the full NIST sets.
+
+use std::{env, format, fs, path::PathBuf, string::String, vec, vec::Vec};
+
+use serde_json::Value;
+
+use crate::{MlKem512, MlKem768, MlKem1024, ParameterSet,
+ml_kem_internal::{check_decaps_key, check_encaps_key, ml_kem_decaps_internal, ml_kem_encaps_internal, ml_kem_keygen_internal}};
+
+struct Params {
+    k: usize,
+    eta1: u8,
+    eta2: u8,
+    du: u8,
+    dv: u8
+}
+
+impl Params {
+    fn of<P: ParameterSet>() -> Self {
+        Params { k: P::K, eta1: P::ETA1, eta2: P::ETA2, du: P::DU, dv: P::DV }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ML-KEM-512" => Some(Self::of::<MlKem512>()),
+            "ML-KEM-768" => Some(Self::of::<MlKem768>()),
+            "ML-KEM-1024" => Some(Self::of::<MlKem1024>()),
+            _ => None
+        }
+    }
+
+    fn ciphertext_len(&self) -> usize {
+        32 * (self.du as usize * self.k + self.dv as usize)
+    }
+}
+
+// A prompt test case with its expected result
+struct Case<'a> {
+    group: &'a Value,
+    prompt: &'a Value,
+    expected: &'a Value
+}
+
+impl Case<'_> {
+    fn id(&self) -> String {
+        format!("tgId {} tcId {}", self.group["tgId"], self.prompt["tcId"])
+    }
+
+    // Test fields take precedence over group fields, older revisions put dk on each test
+    fn field(&self, name: &str) -> Result<&Value, String> {
+        self.prompt.get(name).or_else(|| self.group.get(name)).ok_or_else(|| format!("{}: missing {name}", self.id()))
+    }
+
+    fn bytes(&self, name: &str) -> Result<Vec<u8>, String> {
+        let hex = self.field(name)?.as_str().ok_or_else(|| format!("{}: {name} is not a string", self.id()))?;
+        decode_hex(hex).ok_or_else(|| format!("{}: {name} is not hex", self.id()))
+    }
+
+    fn array<const N: usize>(&self, name: &str) -> Result<[u8; N], String> {
+        self.bytes(name)?.try_into().map_err(|_| format!("{}: {name} is not {N} bytes", self.id()))
+    }
+
+    fn expected_bytes(&self, name: &str) -> Result<Vec<u8>, String> {
+        let hex = self.expected.get(name).and_then(Value::as_str).ok_or_else(|| format!("{}: missing expected {name}", self.id()))?;
+        decode_hex(hex).ok_or_else(|| format!("{}: expected {name} is not hex", self.id()))
+    }
+
+    fn compare(&self, name: &str, got: &[u8]) -> Result<(), String> {
+        let expected = self.expected_bytes(name)?;
+        if got == expected.as_slice() {
+            Ok(())
+        } else {
+            Err(format!("{}: {name} mismatch\n  expected {}\n  got      {}", self.id(), encode_hex(&expected), encode_hex(got)))
+        }
+    }
+
+    fn compare_passed(&self, got: bool) -> Result<(), String> {
+        let expected = self.expected.get("testPassed").and_then(Value::as_bool).ok_or_else(|| format!("{}: missing expected testPassed", self.id()))?;
+        if got == expected {
+            Ok(())
+        } else {
+            Err(format!("{}: testPassed mismatch, expected {expected} got {got}", self.id()))
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+fn vectors_dir() -> PathBuf {
+    match env::var_os("ACVP_VECTORS_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/acvp")
+    }
+}
+
+fn load(vector_set: &str, file: &str) -> Value {
+    let path = vectors_dir().join(vector_set).join(file);
+    let text = fs::read_to_string(&path).unwrap_or_else(|e| panic!("reading {}: {e}", path.display()));
+    let value: Value = serde_json::from_str(&text).unwrap_or_else(|e| panic!("parsing {}: {e}", path.display()));
+    // Responses from an ACVP server wrap the vector set in [{"acvVersion": ..}, {..}]
+    match value {
+        Value::Array(mut items) => items.pop().expect("empty ACVP response"),
+        value => value
+    }
+}
+
+// Runs every test case of a vector set and fails with a line per mismatching case
+fn run_vector_set(vector_set: &str, run_case: impl Fn(&Params, &str, &Case) -> Result<(), String>) {
+    let prompt = load(vector_set, "prompt.json");
+    let expected = load(vector_set, "expectedResults.json");
+    let expected_groups = expected["testGroups"].as_array().expect("expectedResults.json has no testGroups");
+
+    let mut failures = Vec::new();
+    let mut total = 0;
+    for group in prompt["testGroups"].as_array().expect("prompt.json has no testGroups") {
+        let expected_group = expected_groups.iter().find(|g| g["tgId"] == group["tgId"]);
+        let Some(params) = group["parameterSet"].as_str().and_then(Params::from_name) else {
+            failures.push(format!("tgId {}: unknown parameterSet {}", group["tgId"], group["parameterSet"]));
+            continue;
+        };
+        let function = group["function"].as_str().unwrap_or("");
+        for test in group["tests"].as_array().expect("test group has no tests") {
+            total += 1;
+            let expected_test = expected_group.and_then(|g| g["tests"].as_array()?.iter().find(|t| t["tcId"] == test["tcId"]));
+            let Some(expected) = expected_test else {
+                failures.push(format!("tgId {} tcId {}: no expected result", group["tgId"], test["tcId"]));
+                continue;
+            };
+            if let Err(e) = run_case(&params, function, &Case { group, prompt: test, expected }) {
+                failures.push(e);
+            }
+        }
+    }
+
+    assert!(total > 0, "{vector_set} has no test cases");
+    assert!(failures.is_empty(), "{} of {total} {vector_set} cases failed:\n{}", failures.len(), failures.join("\n"));
+}
+
+fn keygen_case(params: &Params, _: &str, case: &Case) -> Result<(), String> {
+    let d = case.array::<32>("d")?;
+    let z = case.array::<32>("z")?;
+    let mut ek = vec![0u8; 384 * params.k + 32];
+    let mut dk = vec![0u8; 768 * params.k + 96];
+    ml_kem_keygen_internal(params.k, params.eta1, &d, &z, &mut ek, &mut dk);
+    case.compare("ek", &ek)?;
+    case.compare("dk", &dk)
+}
+
+fn encap_decap_case(params: &Params, function: &str, case: &Case) -> Result<(), String> {
+    let Params { k, eta1, eta2, du, dv } = *params;
+    match function {
+        "encapsulation" => {
+            let ek = case.bytes("ek")?;
+            if ek.len() != 384 * k + 32 {
+                return Err(format!("{}: ek is {} bytes", case.id(), ek.len()));
+            }
+            let m = case.array::<32>("m")?;
+            let mut c = vec![0u8; params.ciphertext_len()];
+            let ssk = ml_kem_encaps_internal(k, eta1, eta2, du, dv, &ek, &m, &mut c);
+            case.compare("c", &c)?;
+            case.compare("k", &ssk.0)
+        }
+        "decapsulation" => {
+            let dk = case.bytes("dk")?;
+            let c = case.bytes("c")?;
+            if dk.len() != 768 * k + 96 || c.len() != params.ciphertext_len() {
+                return Err(format!("{}: dk is {} bytes and c is {} bytes", case.id(), dk.len(), c.len()));
+            }
+            let ssk = ml_kem_decaps_internal(k, eta1, eta2, du, dv, &dk, &c);
+            case.compare("k", &ssk.0)
+        }
+        "encapsulationKeyCheck" => case.compare_passed(check_encaps_key(k, &case.bytes("ek")?)),
+        "decapsulationKeyCheck" => case.compare_passed(check_decaps_key(k, &case.bytes("dk")?)),
+        _ => Err(format!("{}: unknown function {function}", case.id()))
+    }
+}
+
+#[test]
+fn acvp_keygen() {
+    run_vector_set("ML-KEM-keyGen-FIPS203", keygen_case);
+}
+
+#[test]
+fn acvp_encap_decap() {
+    run_vector_set("ML-KEM-encapDecap-FIPS203", encap_decap_case);
+}
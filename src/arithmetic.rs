@@ -1,25 +1,149 @@
+// Constant-time arithmetic mod q on signed 16-bit coefficients
+//
+// Polynomials passed between modules hold canonical coefficients in [0, q). Inside the NTT and the
+// NTT domain products, coefficients are only bounded in absolute value and reduced lazily, with
+// Montgomery reduction after each multiplication and Barrett reduction where the bounds require it.
 use crate::Q;
 
+// q^-1 mod 2^16, as a signed 16-bit value
+pub(crate) const QINV: i16 = -3327;
+// 2^32 mod q, converts a value out of the Montgomery domain through montgomery_reduce
+pub(crate) const R2: i16 = 1353;
+// round(2^26 / q)
+pub(crate) const BARRETT_V: i32 = ((1 << 26) + Q as i32 / 2) / Q as i32;
+
+// Returns a * 2^-16 mod q in (-q, q), for |a| < q * 2^15
 #[inline(always)]
-pub(crate) fn mod_q_mul(a: u16, b: u16) -> u16 {
-    ((a as u32 * b as u32) % Q as u32) as u16
+pub(crate) fn montgomery_reduce(a: i32) -> i16 {
+    let t = (a as i16).wrapping_mul(QINV);
+    ((a - t as i32 * Q as i32) >> 16) as i16
 }
 
+// Returns a * b * 2^-16 mod q in (-q, q), for |a * b| < q * 2^15
 #[inline(always)]
-pub(crate) fn mod_q_sub(a: u16, b: u16) -> u16 {
-    if a >= b {
-        a - b
-    } else {
-        a + Q - b
-    }
+pub(crate) fn fqmul(a: i16, b: i16) -> i16 {
+    montgomery_reduce(a as i32 * b as i32)
+}
+
+// Returns a mod q in [-(q - 1) / 2, (q - 1) / 2] for any a
+#[inline(always)]
+pub(crate) fn barrett_reduce(a: i16) -> i16 {
+    let t = ((BARRETT_V * a as i32 + (1 << 25)) >> 26) as i16;
+    // t * q can exceed i16 near the ends of the range, the difference always fits
+    a.wrapping_sub(t.wrapping_mul(Q))
+}
+
+// Adds q to a negative a, mapping (-q, q) to [0, q) without a branch
+#[inline(always)]
+pub(crate) fn caddq(a: i16) -> i16 {
+    a + ((a >> 15) & Q)
+}
+
+// Subtracts q from a in [q, 2q), mapping [0, 2q) to [0, q) without a branch
+#[inline(always)]
+pub(crate) fn csubq(a: i16) -> i16 {
+    caddq(a - Q)
+}
+
+// Returns the canonical representative of a in [0, q)
+#[inline(always)]
+pub(crate) fn reduce(a: i16) -> i16 {
+    caddq(barrett_reduce(a))
+}
+
+// Returns the canonical representative of an unsigned 16-bit a in [0, q). Read as i16, a is off by
+// 2^16 when its top bit is set, which the masked 2^16 mod q puts back
+#[cfg(feature = "hazmat")]
+#[inline(always)]
+pub(crate) fn reduce_u16(a: u16) -> i16 {
+    const R: i16 = ((1 << 16) % Q as i32) as i16;
+    mod_q_add(reduce(a as i16), (a as i16 >> 15) & R)
+}
+
+#[inline(always)]
+pub(crate) fn mod_q_sub(a: i16, b: i16) -> i16 {
+    caddq(a - b)
 }
 
 #[inline(always)]
-pub(crate) fn mod_q_add(a: u16, b: u16) -> u16 {
-    let sum = a + b;
-    if sum >= Q {
-        sum - Q
-    } else {
-        sum
+pub(crate) fn mod_q_add(a: i16, b: i16) -> i16 {
+    csubq(a + b)
+}
+
+// Converts a canonical a to the Montgomery domain as a * 2^16 mod q, for the constant tables
+pub(crate) const fn to_mont(a: i16) -> i16 {
+    let r = ((a as i32) << 16) % Q as i32;
+    if r > Q as i32 / 2 { (r - Q as i32) as i16 } else { r as i16 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The previous u16 arithmetic, kept as the reference the reductions must agree with
+    fn ref_mul(a: u16, b: u16) -> u16 {
+        ((a as u32 * b as u32) % Q as u32) as u16
+    }
+
+    fn ref_add(a: u16, b: u16) -> u16 {
+        ((a as u32 + b as u32) % Q as u32) as u16
+    }
+
+    fn ref_sub(a: u16, b: u16) -> u16 {
+        ((a as u32 + Q as u32 - b as u32) % Q as u32) as u16
+    }
+
+    #[test]
+    fn test_matches_u16_arithmetic() {
+        for a in 0..Q {
+            let a_mont = to_mont(a);
+            for b in 0..Q {
+                let (a_u16, b_u16) = (a as u16, b as u16);
+                assert_eq!(caddq(fqmul(a_mont, b)) as u16, ref_mul(a_u16, b_u16), "{a} * {b}");
+                assert_eq!(mod_q_add(a, b) as u16, ref_add(a_u16, b_u16), "{a} + {b}");
+                assert_eq!(mod_q_sub(a, b) as u16, ref_sub(a_u16, b_u16), "{a} - {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_barrett_reduce() {
+        for a in i16::MIN..=i16::MAX {
+            let r = barrett_reduce(a);
+            assert!(r.abs() <= (Q - 1) / 2, "{a} reduced to {r}");
+            assert_eq!((a as i32 - r as i32).rem_euclid(Q as i32), 0, "{a} reduced to {r}");
+            assert_eq!(reduce(a) as i32, (a as i32).rem_euclid(Q as i32));
+        }
+    }
+
+    #[test]
+    fn test_montgomery_reduce() {
+        // Steps through the whole input range |a| < q * 2^15 including both ends
+        let bound = Q as i32 * (1 << 15) - 1;
+        let mut a = -bound;
+        while a <= bound {
+            let r = montgomery_reduce(a);
+            assert!(r.abs() < Q, "{a} reduced to {r}");
+            assert_eq!((r as i64 * (1 << 16) - a as i64).rem_euclid(Q as i64), 0, "{a} reduced to {r}");
+            a += if a < bound - 7919 { 7919 } else { 1 };
+        }
+    }
+
+    #[test]
+    fn test_conditional_reductions() {
+        for a in -Q + 1..Q {
+            assert_eq!(caddq(a) as i32, (a as i32).rem_euclid(Q as i32));
+        }
+        for a in 0..2 * Q {
+            assert_eq!(csubq(a), a % Q);
+        }
+    }
+
+    #[test]
+    fn test_montgomery_domain() {
+        for a in 0..Q {
+            assert_eq!(caddq(fqmul(to_mont(a), 1)), a);
+            assert_eq!(caddq(montgomery_reduce(fqmul(a, R2) as i32)), a);
+        }
     }
 }
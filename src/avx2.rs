@@ -0,0 +1,597 @@
+// AVX2 versions of the NTT and polynomial arithmetic in ntt.rs, of the four-way Keccak
+// permutation in keccak.rs and of the rejection sampling in sample.rs, selected at runtime
+//
+// Each function performs the same reductions as its scalar counterpart on 16 coefficients at a
+// time, so the outputs match bit for bit. NTT layers with len >= 16 pair whole vectors, the layers
+// with len 8, 4 and 2 first rearrange 32 coefficients so that the butterfly pairs line up.
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+use crate::{Q, arithmetic::{BARRETT_V, QINV, R2}, keccak::{KeccakState4, SHAKE128_RATE, keccak_rounds}, ntt::{GAMMAS_MONT, NTT_INV_SCALE, ZETAS_MONT}};
+
+// is_x86_feature_detected! needs std, without it only a target compiled for AVX2 uses this backend
+#[inline]
+pub(crate) fn available() -> bool {
+    #[cfg(any(feature = "std", test))]
+    {
+        std::is_x86_feature_detected!("avx2")
+    }
+    #[cfg(not(any(feature = "std", test)))]
+    {
+        cfg!(target_feature = "avx2")
+    }
+}
+
+// Whether the dispatchers pick this backend, the portable feature keeps them on the portable code
+#[inline]
+pub(crate) fn enabled() -> bool {
+    !cfg!(feature = "portable") && available()
+}
+
+// Coefficient offsets, within 32 coefficients, of the first butterfly input in each lane once the
+// len 8, 4 and 2 layers have rearranged them. Only the butterfly, and so the zeta, of a lane matters.
+const LANES_LEN_8: [usize; 16] = [0, 1, 2, 3, 4, 5, 6, 7, 16, 17, 18, 19, 20, 21, 22, 23];
+const LANES_LEN_4: [usize; 16] = [0, 1, 2, 3, 16, 17, 18, 19, 8, 9, 10, 11, 24, 25, 26, 27];
+const LANES_LEN_2: [usize; 16] = [0, 1, 4, 5, 16, 17, 20, 21, 8, 9, 12, 13, 24, 25, 28, 29];
+
+// The zeta of each lane for the eight 32 coefficient chunks of a layer
+const fn layer_zetas(len: usize, lanes: &[usize; 16], inverse: bool) -> [[i16; 16]; 8] {
+    let mut table = [[0i16; 16]; 8];
+    let mut chunk = 0;
+    while chunk < 8 {
+        let mut lane = 0;
+        while lane < 16 {
+            let block = (32 * chunk + lanes[lane]) / (2 * len);
+            // The forward NTT walks the zetas up from 128 / len, the inverse down from 256 / len - 1
+            let k = if inverse { 256 / len - 1 - block } else { 128 / len + block };
+            table[chunk][lane] = ZETAS_MONT[k];
+            lane += 1;
+        }
+        chunk += 1;
+    }
+    table
+}
+
+const NTT_ZETAS_8: [[i16; 16]; 8] = layer_zetas(8, &LANES_LEN_8, false);
+const NTT_ZETAS_4: [[i16; 16]; 8] = layer_zetas(4, &LANES_LEN_4, false);
+const NTT_ZETAS_2: [[i16; 16]; 8] = layer_zetas(2, &LANES_LEN_2, false);
+const NTT_INV_ZETAS_8: [[i16; 16]; 8] = layer_zetas(8, &LANES_LEN_8, true);
+const NTT_INV_ZETAS_4: [[i16; 16]; 8] = layer_zetas(4, &LANES_LEN_4, true);
+const NTT_INV_ZETAS_2: [[i16; 16]; 8] = layer_zetas(2, &LANES_LEN_2, true);
+
+// The gamma of each coefficient pair in its odd lane, for the sixteen 16 coefficient chunks
+const fn pair_gammas() -> [[i16; 16]; 16] {
+    let mut table = [[0i16; 16]; 16];
+    let mut chunk = 0;
+    while chunk < 16 {
+        let mut pair = 0;
+        while pair < 8 {
+            table[chunk][2 * pair + 1] = GAMMAS_MONT[8 * chunk + pair];
+            pair += 1;
+        }
+        chunk += 1;
+    }
+    table
+}
+
+const GAMMAS: [[i16; 16]; 16] = pair_gammas();
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn load(x: &[i16; 16]) -> __m256i {
+    // SAFETY: x is 32 readable bytes and loadu has no alignment requirement
+    unsafe { _mm256_loadu_si256(x.as_ptr().cast()) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn store(x: &mut [i16; 16], v: __m256i) {
+    // SAFETY: x is 32 writable bytes and storeu has no alignment requirement
+    unsafe { _mm256_storeu_si256(x.as_mut_ptr().cast(), v) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn load_i32(x: &[i32; 8]) -> __m256i {
+    // SAFETY: x is 32 readable bytes and loadu has no alignment requirement
+    unsafe { _mm256_loadu_si256(x.as_ptr().cast()) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn store_i32(x: &mut [i32; 8], v: __m256i) {
+    // SAFETY: x is 32 writable bytes and storeu has no alignment requirement
+    unsafe { _mm256_storeu_si256(x.as_mut_ptr().cast(), v) }
+}
+
+// arithmetic::fqmul, the low halves of a * b and t * q cancel so the high halves give the result
+#[inline]
+#[target_feature(enable = "avx2")]
+fn fqmul(a: __m256i, b: __m256i) -> __m256i {
+    let t = _mm256_mullo_epi16(_mm256_mullo_epi16(a, b), _mm256_set1_epi16(QINV));
+    _mm256_sub_epi16(_mm256_mulhi_epi16(a, b), _mm256_mulhi_epi16(t, _mm256_set1_epi16(Q)))
+}
+
+// arithmetic::barrett_reduce, rounding (a * v) >> 16 by a further 10 bits gives the same quotient
+#[inline]
+#[target_feature(enable = "avx2")]
+fn barrett_reduce(a: __m256i) -> __m256i {
+    let t = _mm256_mulhi_epi16(a, _mm256_set1_epi16(BARRETT_V as i16));
+    let t = _mm256_srai_epi16::<10>(_mm256_add_epi16(t, _mm256_set1_epi16(1 << 9)));
+    _mm256_sub_epi16(a, _mm256_mullo_epi16(t, _mm256_set1_epi16(Q)))
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn caddq(a: __m256i) -> __m256i {
+    _mm256_add_epi16(a, _mm256_and_si256(_mm256_srai_epi16::<15>(a), _mm256_set1_epi16(Q)))
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn csubq(a: __m256i) -> __m256i {
+    caddq(_mm256_sub_epi16(a, _mm256_set1_epi16(Q)))
+}
+
+// The forward butterfly of ntt
+#[inline]
+#[target_feature(enable = "avx2")]
+fn butterfly(a: __m256i, b: __m256i, zeta: __m256i) -> (__m256i, __m256i) {
+    let t = fqmul(zeta, b);
+    (_mm256_add_epi16(a, t), _mm256_sub_epi16(a, t))
+}
+
+// The inverse butterfly of ntt_inv
+#[inline]
+#[target_feature(enable = "avx2")]
+fn butterfly_inv(a: __m256i, b: __m256i, zeta: __m256i) -> (__m256i, __m256i) {
+    (barrett_reduce(_mm256_add_epi16(a, b)), fqmul(zeta, _mm256_sub_epi16(b, a)))
+}
+
+// Separates the butterfly inputs of 32 coefficients x || y into a and b for the layers with len < 16.
+// The len 8 and len 4 splits are their own inverse, join_len_2 undoes split_len_2.
+#[inline]
+#[target_feature(enable = "avx2")]
+fn split_len_8(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
+    (_mm256_permute2x128_si256::<0x20>(x, y), _mm256_permute2x128_si256::<0x31>(x, y))
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn split_len_4(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
+    (_mm256_unpacklo_epi64(x, y), _mm256_unpackhi_epi64(x, y))
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn split_len_2(x: __m256i, y: __m256i) -> (__m256i, __m256i) {
+    // Gathers the even and odd 32-bit words of each 128-bit lane into its low and high half
+    let (x, y) = (_mm256_shuffle_epi32::<0b11_01_10_00>(x), _mm256_shuffle_epi32::<0b11_01_10_00>(y));
+    (_mm256_unpacklo_epi64(x, y), _mm256_unpackhi_epi64(x, y))
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn join_len_2(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+    let (x, y) = (_mm256_unpacklo_epi64(a, b), _mm256_unpackhi_epi64(a, b));
+    (_mm256_shuffle_epi32::<0b11_01_10_00>(x), _mm256_shuffle_epi32::<0b11_01_10_00>(y))
+}
+
+#[target_feature(enable = "avx2")]
+pub(crate) fn ntt(f: &[i16; 256]) -> [i16; 256] {
+    let mut f_ntt = *f;
+    let mut k = 1;
+
+    let mut len = 128;
+    while len >= 16 {
+        for start in (0..256).step_by(2 * len) {
+            let zeta = _mm256_set1_epi16(ZETAS_MONT[k]);
+            k += 1;
+            let (lo, hi) = f_ntt[start..start + 2 * len].split_at_mut(len);
+            for (a, b) in lo.as_chunks_mut::<16>().0.iter_mut().zip(hi.as_chunks_mut::<16>().0) {
+                let (a_out, b_out) = butterfly(load(a), load(b), zeta);
+                store(a, a_out);
+                store(b, b_out);
+            }
+        }
+        len /= 2;
+    }
+
+    for (chunk, f_chunk) in f_ntt.as_chunks_mut::<32>().0.iter_mut().enumerate() {
+        let [x, y] = f_chunk.as_chunks_mut::<16>().0 else { unreachable!() };
+
+        let (a, b) = split_len_8(load(x), load(y));
+        let (a, b) = butterfly(a, b, load(&NTT_ZETAS_8[chunk]));
+        let (x_out, y_out) = split_len_8(a, b);
+        let (a, b) = split_len_4(x_out, y_out);
+        let (a, b) = butterfly(a, b, load(&NTT_ZETAS_4[chunk]));
+        let (x_out, y_out) = split_len_4(a, b);
+        let (a, b) = split_len_2(x_out, y_out);
+        let (a, b) = butterfly(a, b, load(&NTT_ZETAS_2[chunk]));
+        let (x_out, y_out) = join_len_2(a, b);
+
+        // Every layer adds less than q to the coefficients, a single reduction at the end suffices
+        store(x, caddq(barrett_reduce(x_out)));
+        store(y, caddq(barrett_reduce(y_out)));
+    }
+    f_ntt
+}
+
+#[target_feature(enable = "avx2")]
+pub(crate) fn ntt_inv(f_ntt: &[i16; 256]) -> [i16; 256] {
+    let mut f = *f_ntt;
+
+    for (chunk, f_chunk) in f.as_chunks_mut::<32>().0.iter_mut().enumerate() {
+        let [x, y] = f_chunk.as_chunks_mut::<16>().0 else { unreachable!() };
+
+        let (a, b) = split_len_2(load(x), load(y));
+        let (a, b) = butterfly_inv(a, b, load(&NTT_INV_ZETAS_2[chunk]));
+        let (x_out, y_out) = join_len_2(a, b);
+        let (a, b) = split_len_4(x_out, y_out);
+        let (a, b) = butterfly_inv(a, b, load(&NTT_INV_ZETAS_4[chunk]));
+        let (x_out, y_out) = split_len_4(a, b);
+        let (a, b) = split_len_8(x_out, y_out);
+        let (a, b) = butterfly_inv(a, b, load(&NTT_INV_ZETAS_8[chunk]));
+        let (x_out, y_out) = split_len_8(a, b);
+
+        store(x, x_out);
+        store(y, y_out);
+    }
+
+    let mut k = 15;
+    let mut len = 16;
+    while len <= 128 {
+        for start in (0..256).step_by(2 * len) {
+            let zeta = _mm256_set1_epi16(ZETAS_MONT[k]);
+            k -= 1;
+            let (lo, hi) = f[start..start + 2 * len].split_at_mut(len);
+            for (a, b) in lo.as_chunks_mut::<16>().0.iter_mut().zip(hi.as_chunks_mut::<16>().0) {
+                let (a_out, b_out) = butterfly_inv(load(a), load(b), zeta);
+                store(a, a_out);
+                store(b, b_out);
+            }
+        }
+        len *= 2;
+    }
+
+    let scale = _mm256_set1_epi16(NTT_INV_SCALE);
+    for x in f.as_chunks_mut::<16>().0 {
+        store(x, caddq(fqmul(load(x), scale)));
+    }
+    f
+}
+
+// ntt::multiply_ntts_acc, madd sums the two products of each output coefficient in 32 bits
+#[target_feature(enable = "avx2")]
+pub(crate) fn multiply_ntts_acc(f_ntt: &[i16; 256], g_ntt: &[i16; 256], acc: &mut [i32; 256]) {
+    let chunks = f_ntt.as_chunks::<16>().0.iter().zip(g_ntt.as_chunks::<16>().0).zip(acc.as_chunks_mut::<16>().0);
+    for (chunk, ((f, g), acc)) in chunks.enumerate() {
+        let (f, g) = (load(f), load(g));
+        // g with each odd coefficient multiplied by its gamma, and g with the coefficients of each pair swapped
+        let g_gamma = _mm256_blend_epi16::<0b1010_1010>(g, fqmul(g, load(&GAMMAS[chunk])));
+        let g_swapped = _mm256_or_si256(_mm256_slli_epi32::<16>(g), _mm256_srli_epi32::<16>(g));
+        let even = _mm256_madd_epi16(f, g_gamma);
+        let odd = _mm256_madd_epi16(f, g_swapped);
+
+        // Interleave the even and odd outputs back into coefficient order
+        let (lo, hi) = (_mm256_unpacklo_epi32(even, odd), _mm256_unpackhi_epi32(even, odd));
+        let [acc_lo, acc_hi] = acc.as_chunks_mut::<8>().0 else { unreachable!() };
+        store_i32(acc_lo, _mm256_add_epi32(load_i32(acc_lo), _mm256_permute2x128_si256::<0x20>(lo, hi)));
+        store_i32(acc_hi, _mm256_add_epi32(load_i32(acc_hi), _mm256_permute2x128_si256::<0x31>(lo, hi)));
+    }
+}
+
+// arithmetic::montgomery_reduce on 32-bit lanes, leaving the 16-bit result sign extended
+#[inline]
+#[target_feature(enable = "avx2")]
+fn montgomery_reduce_i32(a: __m256i) -> __m256i {
+    let t = _mm256_mullo_epi32(a, _mm256_set1_epi32(QINV as i32));
+    let t = _mm256_srai_epi32::<16>(_mm256_slli_epi32::<16>(t));
+    _mm256_srai_epi32::<16>(_mm256_sub_epi32(a, _mm256_mullo_epi32(t, _mm256_set1_epi32(Q as i32))))
+}
+
+// ntt::reduce_acc
+#[target_feature(enable = "avx2")]
+pub(crate) fn reduce_acc(acc: &[i32; 256]) -> [i16; 256] {
+    let mut result = [0i16; 256];
+    let r2 = _mm256_set1_epi16(R2);
+    for (acc, out) in acc.as_chunks::<16>().0.iter().zip(result.as_chunks_mut::<16>().0) {
+        let [acc_lo, acc_hi] = acc.as_chunks::<8>().0 else { unreachable!() };
+        let packed = _mm256_packs_epi32(montgomery_reduce_i32(load_i32(acc_lo)), montgomery_reduce_i32(load_i32(acc_hi)));
+        // packs interleaves the 64-bit quarters of its inputs, put them back in order
+        let reduced = _mm256_permute4x64_epi64::<0b11_01_10_00>(packed);
+        store(out, caddq(fqmul(reduced, r2)));
+    }
+    result
+}
+
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_add(a: &mut [i16; 256], b: &[i16; 256]) {
+    for (a, b) in a.as_chunks_mut::<16>().0.iter_mut().zip(b.as_chunks::<16>().0) {
+        store(a, csubq(_mm256_add_epi16(load(a), load(b))));
+    }
+}
+
+#[target_feature(enable = "avx2")]
+pub(crate) fn poly_sub(a: &[i16; 256], b: &[i16; 256]) -> [i16; 256] {
+    let mut result = [0i16; 256];
+    for ((a, b), out) in a.as_chunks::<16>().0.iter().zip(b.as_chunks::<16>().0).zip(result.as_chunks_mut::<16>().0) {
+        store(out, caddq(_mm256_sub_epi16(load(a), load(b))));
+    }
+    result
+}
+
+// Word i of the four states in one register
+#[inline]
+#[target_feature(enable = "avx2")]
+fn load_u64(x: &[u64; 4]) -> __m256i {
+    // SAFETY: x is 32 readable bytes and loadu has no alignment requirement
+    unsafe { _mm256_loadu_si256(x.as_ptr().cast()) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn store_u64(x: &mut [u64; 4], v: __m256i) {
+    // SAFETY: x is 32 writable bytes and storeu has no alignment requirement
+    unsafe { _mm256_storeu_si256(x.as_mut_ptr().cast(), v) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn xor(a: __m256i, b: __m256i) -> __m256i {
+    _mm256_xor_si256(a, b)
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn andnot(a: __m256i, b: __m256i) -> __m256i {
+    _mm256_andnot_si256(a, b)
+}
+
+// Shifts of 64 bits or more give zero, so a rotation by 0 leaves the word unchanged
+#[inline]
+#[target_feature(enable = "avx2")]
+fn rotl(a: __m256i, n: u32) -> __m256i {
+    let left = _mm256_sll_epi64(a, _mm_cvtsi32_si128(n as i32));
+    let right = _mm256_srl_epi64(a, _mm_cvtsi32_si128(64 - n as i32));
+    _mm256_or_si256(left, right)
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn splat(w: u64) -> __m256i {
+    _mm256_set1_epi64x(w as i64)
+}
+
+// keccak::keccak_f1600_x4_portable
+#[target_feature(enable = "avx2")]
+pub(crate) fn keccak_f1600_x4(state: &mut KeccakState4) {
+    let mut a = [_mm256_setzero_si256(); 25];
+    for (a, word) in a.iter_mut().zip(state.iter()) {
+        *a = load_u64(word);
+    }
+    keccak_rounds!(a, xor, andnot, rotl, splat);
+    for (word, a) in state.iter_mut().zip(a) {
+        store_u64(word, a);
+    }
+}
+
+// For each 8-bit mask of accepted candidates, the byte offsets of the accepted 16-bit candidates
+// followed by 0xff. Only public XOF output for the matrix A is sampled, so the lookup leaks nothing.
+const fn accept_shuffles() -> [[u8; 8]; 256] {
+    let mut table = [[0xffu8; 8]; 256];
+    let mut mask = 0;
+    while mask < 256 {
+        let (mut i, mut n) = (0, 0);
+        while i < 8 {
+            if mask & (1 << i) != 0 {
+                table[mask][n] = 2 * i as u8;
+                n += 1;
+            }
+            i += 1;
+        }
+        mask += 1;
+    }
+    table
+}
+
+const ACCEPT_SHUFFLES: [[u8; 8]; 256] = accept_shuffles();
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn load_u8(x: &[u8; 16]) -> __m128i {
+    // SAFETY: x is 16 readable bytes and loadu has no alignment requirement
+    unsafe { _mm_loadu_si128(x.as_ptr().cast()) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+fn store_half(x: &mut [i16; 8], v: __m128i) {
+    // SAFETY: x is 16 writable bytes and storeu has no alignment requirement
+    unsafe { _mm_storeu_si128(x.as_mut_ptr().cast(), v) }
+}
+
+// The shuffle packing the accepted candidates of one 128-bit half to its start
+#[inline]
+#[target_feature(enable = "avx2")]
+fn accept_shuffle(mask: u32) -> __m128i {
+    _mm_set_epi64x(0, u64::from_le_bytes(ACCEPT_SHUFFLES[mask as usize]) as i64)
+}
+
+// sample::sample_bytes_scalar on 24 bytes, or 16 candidates, at a time while all of them fit in a.
+// Returns the number of bytes parsed, the rest of the block is left to the scalar code.
+#[target_feature(enable = "avx2")]
+pub(crate) fn sample_block(block: &[u8; SHAKE128_RATE], a: &mut [i16; 256], j: &mut usize) -> usize {
+    // Each candidate pair is three bytes, d1 in the low 12 bits of the first two and d2 in the high
+    // 12 bits of the last two. The low half takes bytes 0 to 11 and the high half bytes 12 to 23.
+    let pairs = _mm256_setr_epi8(
+        0, 1, 1, 2, 3, 4, 4, 5, 6, 7, 7, 8, 9, 10, 10, 11,
+        4, 5, 5, 6, 7, 8, 8, 9, 10, 11, 11, 12, 13, 14, 14, 15,
+    );
+    let mut parsed = 0;
+    for chunk in block.as_chunks::<24>().0 {
+        if *j > 256 - 16 {
+            break;
+        }
+        let bytes = _mm256_set_m128i(load_u8(chunk.last_chunk().unwrap()), load_u8(chunk.first_chunk().unwrap()));
+        let f = _mm256_shuffle_epi8(bytes, pairs);
+        let f = _mm256_blend_epi16::<0xAA>(f, _mm256_srli_epi16::<4>(f));
+        let f = _mm256_and_si256(f, _mm256_set1_epi16(0xfff));
+
+        // Bits 0 to 7 of the mask cover the low half and bits 16 to 23 the high half
+        let accept = _mm256_cmpgt_epi16(_mm256_set1_epi16(Q), f);
+        let mask = _mm256_movemask_epi8(_mm256_packs_epi16(accept, accept)) as u32;
+        let (mask_low, mask_high) = (mask & 0xff, (mask >> 16) & 0xff);
+        let shuffle = _mm256_set_m128i(accept_shuffle(mask_high), accept_shuffle(mask_low));
+        let shuffle = _mm256_unpacklo_epi8(shuffle, _mm256_add_epi8(shuffle, _mm256_set1_epi8(1)));
+        let f = _mm256_shuffle_epi8(f, shuffle);
+
+        // The lanes past the accepted candidates are overwritten by the next ones
+        store_half(a[*j..].first_chunk_mut().unwrap(), _mm256_castsi256_si128(f));
+        *j += mask_low.count_ones() as usize;
+        store_half(a[*j..].first_chunk_mut().unwrap(), _mm256_extracti128_si256::<1>(f));
+        *j += mask_high.count_ones() as usize;
+        parsed += 24;
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_K, keccak::keccak_f1600_x4_portable, sample::sample_bytes_scalar, ntt::{multiply_ntts_acc_scalar, ntt_inv_scalar, ntt_scalar, poly_add_scalar, poly_sub_scalar, reduce_acc_scalar}};
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{RngCore, SeedableRng};
+    use std::{eprintln, vec::Vec};
+
+    // Random canonical polynomials followed by the all zero and all q - 1 extremes
+    fn test_polys(count: usize) -> Vec<[i16; 256]> {
+        let mut rng = ChaCha20Rng::seed_from_u64(19);
+        let mut polys: Vec<[i16; 256]> = (0..count).map(|_| core::array::from_fn(|_| (rng.next_u32() % Q as u32) as i16)).collect();
+        polys.extend([[0i16; 256], [Q - 1; 256]]);
+        polys
+    }
+
+    // The tests pass trivially on CPUs without AVX2, where the backend is never selected
+    fn skip() -> bool {
+        if !available() {
+            eprintln!("AVX2 is not available, skipping");
+        }
+        !available()
+    }
+
+    #[test]
+    fn test_ntt_matches_scalar() {
+        if skip() {
+            return;
+        }
+        for f in test_polys(500) {
+            // SAFETY: AVX2 is available
+            let (f_ntt, f_inv) = unsafe { (ntt(&f), ntt_inv(&f)) };
+            assert_eq!(f_ntt, ntt_scalar(&f));
+            assert_eq!(f_inv, ntt_inv_scalar(&f));
+        }
+    }
+
+    #[test]
+    fn test_multiply_ntts_acc_matches_scalar() {
+        if skip() {
+            return;
+        }
+        let polys = test_polys(4 * MAX_K * 50);
+        // Accumulating MAX_K products covers the largest sums reduce_acc receives
+        for group in polys.chunks_exact(2 * MAX_K) {
+            let (mut acc, mut acc_scalar) = ([0i32; 256], [0i32; 256]);
+            for pair in group.chunks_exact(2) {
+                // SAFETY: AVX2 is available
+                unsafe { multiply_ntts_acc(&pair[0], &pair[1], &mut acc) };
+                multiply_ntts_acc_scalar(&pair[0], &pair[1], &mut acc_scalar);
+                assert_eq!(acc, acc_scalar);
+            }
+            // SAFETY: AVX2 is available
+            assert_eq!(unsafe { reduce_acc(&acc) }, reduce_acc_scalar(&acc_scalar));
+        }
+
+        let extreme = [Q - 1; 256];
+        let (mut acc, mut acc_scalar) = ([0i32; 256], [0i32; 256]);
+        for _ in 0..MAX_K {
+            // SAFETY: AVX2 is available
+            unsafe { multiply_ntts_acc(&extreme, &extreme, &mut acc) };
+            multiply_ntts_acc_scalar(&extreme, &extreme, &mut acc_scalar);
+        }
+        assert_eq!(acc, acc_scalar);
+        // SAFETY: AVX2 is available
+        assert_eq!(unsafe { reduce_acc(&acc) }, reduce_acc_scalar(&acc_scalar));
+    }
+
+    #[test]
+    fn test_poly_add_sub_matches_scalar() {
+        if skip() {
+            return;
+        }
+        let polys = test_polys(200);
+        for (a, b) in polys.iter().zip(polys.iter().rev()) {
+            let (mut sum, mut sum_scalar) = (*a, *a);
+            // SAFETY: AVX2 is available
+            unsafe { poly_add(&mut sum, b) };
+            poly_add_scalar(&mut sum_scalar, b);
+            assert_eq!(sum, sum_scalar);
+            // SAFETY: AVX2 is available
+            assert_eq!(unsafe { poly_sub(a, b) }, poly_sub_scalar(a, b));
+        }
+    }
+
+    #[test]
+    fn test_keccak_f1600_x4_matches_portable() {
+        if skip() {
+            return;
+        }
+        let mut rng = ChaCha20Rng::seed_from_u64(20);
+        let mut state = [[0u64; 4]; 25];
+        for _ in 0..100 {
+            for word in &mut state {
+                *word = core::array::from_fn(|_| rng.next_u64());
+            }
+            let mut expected = state;
+            keccak_f1600_x4_portable(&mut expected);
+            // SAFETY: AVX2 is available
+            unsafe { keccak_f1600_x4(&mut state) };
+            assert_eq!(state, expected);
+        }
+    }
+
+    #[test]
+    fn test_sample_block_matches_scalar() {
+        if skip() {
+            return;
+        }
+        let mut rng = ChaCha20Rng::seed_from_u64(21);
+        let mut blocks = Vec::new();
+        for _ in 0..500 {
+            let mut block = [0u8; SHAKE128_RATE];
+            rng.fill_bytes(&mut block);
+            blocks.push(block);
+        }
+        // Every candidate accepted or rejected, and candidates on either side of q
+        blocks.extend([[0u8; SHAKE128_RATE], [0xff; SHAKE128_RATE]]);
+        // d1 = q - 1 and d2 = q
+        let around_q = [0x00, 0x1d, 0xd0];
+        blocks.push(core::array::from_fn(|i| around_q[i % 3]));
+
+        for (n, block) in blocks.iter().enumerate() {
+            // Starting points that leave room for every candidate, for some, or for none
+            let start = [0, 100, 200, 239, 240, 241, 250, 255, 256][n % 9];
+            let (mut a, mut a_scalar) = ([-1i16; 256], [-1i16; 256]);
+            let (mut j, mut j_scalar) = (start, start);
+            // SAFETY: AVX2 is available
+            let parsed = unsafe { sample_block(block, &mut a, &mut j) };
+            sample_bytes_scalar(&block[parsed..], &mut a, &mut j);
+            sample_bytes_scalar(block, &mut a_scalar, &mut j_scalar);
+            assert_eq!(j, j_scalar);
+            assert_eq!(a[..j], a_scalar[..j]);
+        }
+    }
+}
@@ -0,0 +1,361 @@
+// Command-line tool for producing and checking ML-KEM keys, ciphertexts and shared secrets
+use std::{error::Error, fs, io::{self, Read, Write}, process::ExitCode};
+
+use base64ct::{Base64, Encoding};
+use clap::{Parser, Subcommand, ValueEnum};
+use fips203_rust::{MlKem, MlKemParams, pkcs8::PrivateKeyFormat, types::{CipherText, DecapsKey, DecapsKeySeed, EncapsKey}};
+use zeroize::Zeroizing;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(name = "fips203", version, about = "ML-KEM (FIPS 203) key generation, encapsulation and decapsulation")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a key pair, from a random or a given seed
+    Keygen {
+        /// Parameter set: 512, 768 or 1024
+        #[arg(short, long, value_parser = parse_params, default_value = "768")]
+        params: MlKemParams,
+        /// File with the 64-byte seed d || z (raw, hex or base64)
+        #[arg(long)]
+        seed: Option<String>,
+        /// Output file for the encapsulation key
+        #[arg(long)]
+        ek_out: String,
+        /// Output file for the decapsulation key
+        #[arg(long)]
+        dk_out: String,
+        #[arg(short, long, value_enum, default_value_t = Format::Pem)]
+        format: Format,
+        /// Contents of a PEM decapsulation key, other formats hold the expanded key
+        #[arg(long, value_enum, default_value_t = DkContents::Both)]
+        dk_contents: DkContents,
+    },
+    /// Encapsulate a shared secret to an encapsulation key
+    Encaps {
+        /// Encapsulation key file
+        #[arg(long)]
+        ek: String,
+        /// Output file for the ciphertext
+        #[arg(long)]
+        ct_out: String,
+        /// Output file for the shared secret
+        #[arg(long)]
+        ss_out: String,
+        /// Format of the ciphertext and shared secret
+        #[arg(short, long, value_enum, default_value_t = Format::Hex)]
+        format: Format,
+    },
+    /// Decapsulate the shared secret from a ciphertext
+    Decaps {
+        /// Decapsulation key file
+        #[arg(long)]
+        dk: String,
+        /// Ciphertext file
+        #[arg(long)]
+        ct: String,
+        /// Output file for the shared secret
+        #[arg(long)]
+        ss_out: String,
+        #[arg(short, long, value_enum, default_value_t = Format::Hex)]
+        format: Format,
+    },
+    /// Write the encapsulation key embedded in a decapsulation key
+    Pubkey {
+        /// Decapsulation key file
+        #[arg(long)]
+        dk: String,
+        /// Output file for the encapsulation key
+        #[arg(long)]
+        ek_out: String,
+        #[arg(short, long, value_enum, default_value_t = Format::Pem)]
+        format: Format,
+    },
+    /// Describe a key or ciphertext file and run the FIPS 203 input checks on it
+    Inspect {
+        file: String,
+    },
+}
+
+// Input files are detected as PEM, hex, base64 or raw bytes
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Raw,
+    Hex,
+    Base64,
+    Pem,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DkContents {
+    Seed,
+    Expanded,
+    Both,
+}
+
+const ALL_PARAMS: [MlKemParams; 3] = [MlKemParams::MlKem512, MlKemParams::MlKem768, MlKemParams::MlKem1024];
+
+fn parse_params(value: &str) -> std::result::Result<MlKemParams, String> {
+    match value.to_ascii_uppercase().trim_start_matches("ML-KEM-").trim_start_matches("MLKEM") {
+        "512" => Ok(MlKemParams::MlKem512),
+        "768" => Ok(MlKemParams::MlKem768),
+        "1024" => Ok(MlKemParams::MlKem1024),
+        _ => Err(format!("unknown parameter set {value}, expected 512, 768 or 1024")),
+    }
+}
+
+fn params_name(params: MlKemParams) -> &'static str {
+    match params {
+        MlKemParams::MlKem512 => "ML-KEM-512",
+        MlKemParams::MlKem768 => "ML-KEM-768",
+        MlKemParams::MlKem1024 => "ML-KEM-1024",
+    }
+}
+
+fn read_file(path: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let mut contents = Zeroizing::new(Vec::new());
+    if path == "-" {
+        io::stdin().read_to_end(&mut contents)?;
+    } else {
+        *contents = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    }
+    Ok(contents)
+}
+
+fn is_pem(contents: &[u8]) -> bool {
+    contents.trim_ascii_start().starts_with(b"-----BEGIN ")
+}
+
+fn decode_hex(text: &[u8]) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    text.chunks_exact(2).map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()).collect()
+}
+
+// Hex or base64 text, possibly wrapped over several lines, otherwise raw bytes
+fn decode_data(contents: &[u8]) -> Zeroizing<Vec<u8>> {
+    let text: Zeroizing<Vec<u8>> = Zeroizing::new(contents.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect());
+    if let Some(bytes) = decode_hex(&text) {
+        return Zeroizing::new(bytes);
+    }
+    if let Ok(bytes) = std::str::from_utf8(&text).map_err(|_| ()).and_then(|t| Base64::decode_vec(t).map_err(|_| ())) {
+        return Zeroizing::new(bytes);
+    }
+    Zeroizing::new(contents.to_vec())
+}
+
+// Text formats end with a newline, the buffer is sized up front so secrets are never reallocated
+fn encode_data(bytes: &[u8], format: Format) -> Result<Zeroizing<Vec<u8>>> {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut encoded = Zeroizing::new(Vec::new());
+    match format {
+        Format::Raw => encoded.extend_from_slice(bytes),
+        Format::Hex => {
+            encoded.reserve_exact(2 * bytes.len() + 1);
+            for b in bytes {
+                encoded.extend_from_slice(&[HEX[(b >> 4) as usize], HEX[(b & 0xF) as usize]]);
+            }
+            encoded.push(b'\n');
+        }
+        Format::Base64 => {
+            encoded.resize(Base64::encoded_len(bytes) + 1, b'\n');
+            let len = encoded.len();
+            Base64::encode(bytes, &mut encoded[..len - 1]).map_err(|e| e.to_string())?;
+        }
+        Format::Pem => return Err("PEM is only defined for keys, use raw, hex or base64".into()),
+    }
+    Ok(encoded)
+}
+
+fn write_file(path: &str, contents: &[u8], secret: bool) -> Result<()> {
+    if path == "-" {
+        io::stdout().write_all(contents)?;
+        return Ok(());
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if secret {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = secret;
+    options.open(path).and_then(|mut file| file.write_all(contents)).map_err(|e| format!("{path}: {e}"))?;
+    Ok(())
+}
+
+fn load_encaps_key(path: &str) -> Result<(MlKemParams, EncapsKey)> {
+    let contents = read_file(path)?;
+    if is_pem(&contents) {
+        let pem = std::str::from_utf8(&contents)?;
+        return EncapsKey::from_public_key_pem(pem).map_err(|e| format!("{path}: {e}").into());
+    }
+    let bytes = decode_data(&contents);
+    let params = MlKemParams::from_encaps_key_len(bytes.len()).ok_or_else(|| format!("{path}: {} bytes is not an encapsulation key length", bytes.len()))?;
+    Ok((params, EncapsKey::from_slice(&bytes)))
+}
+
+fn load_decaps_key(path: &str) -> Result<(MlKemParams, DecapsKey)> {
+    let contents = read_file(path)?;
+    if is_pem(&contents) {
+        let pem = std::str::from_utf8(&contents)?;
+        return DecapsKey::from_pkcs8_pem(pem).map_err(|e| format!("{path}: {e}").into());
+    }
+    let bytes = decode_data(&contents);
+    let params = MlKemParams::from_decaps_key_len(bytes.len()).ok_or_else(|| format!("{path}: {} bytes is not a decapsulation key length", bytes.len()))?;
+    Ok((params, DecapsKey::from_slice(&bytes)))
+}
+
+fn load_seed(path: &str) -> Result<DecapsKeySeed> {
+    let bytes = decode_data(&read_file(path)?);
+    let seed: [u8; 64] = bytes.as_slice().try_into().map_err(|_| format!("{path}: the seed must be 64 bytes, found {}", bytes.len()))?;
+    Ok(DecapsKeySeed::from(seed))
+}
+
+fn write_encaps_key(path: &str, params: MlKemParams, ek: EncapsKey, format: Format) -> Result<()> {
+    let contents = match format {
+        Format::Pem => Zeroizing::new(ek.to_public_key_pem()?.into_bytes()),
+        _ => encode_data(&ek.into_bytes(), format)?,
+    };
+    write_file(path, &contents, false)?;
+    eprintln!("wrote {} encapsulation key to {path}", params_name(params));
+    Ok(())
+}
+
+fn keygen(params: MlKemParams, seed: Option<String>, ek_out: &str, dk_out: &str, format: Format, dk_contents: DkContents) -> Result<()> {
+    let seed = match seed {
+        Some(path) => load_seed(&path)?,
+        None => DecapsKeySeed::generate().map_err(|e| format!("random number generator failure: {e}"))?,
+    };
+    let (ek, dk) = MlKem::new(params).keygen_from_seed(&seed);
+
+    let dk_contents = match format {
+        Format::Pem => {
+            let pem_format = match dk_contents {
+                DkContents::Seed => PrivateKeyFormat::Seed,
+                DkContents::Expanded => PrivateKeyFormat::ExpandedKey,
+                DkContents::Both => PrivateKeyFormat::Both,
+            };
+            Zeroizing::new(seed.to_pkcs8_pem(params, pem_format).as_bytes().to_vec())
+        }
+        _ => encode_data(&dk.into_bytes(), format)?,
+    };
+    write_file(dk_out, &dk_contents, true)?;
+    eprintln!("wrote {} decapsulation key to {dk_out}", params_name(params));
+    write_encaps_key(ek_out, params, ek, format)
+}
+
+fn encaps(ek_path: &str, ct_out: &str, ss_out: &str, format: Format) -> Result<()> {
+    let (params, ek) = load_encaps_key(ek_path)?;
+    let (ss, ct) = MlKem::new(params).encaps(&ek).map_err(|e| format!("{ek_path}: {e}"))?;
+
+    let ct_contents = encode_data(&ct.into_bytes(), format)?;
+    let ss_contents = encode_data(&ss.into_bytes()[..], format)?;
+    write_file(ct_out, &ct_contents, false)?;
+    write_file(ss_out, &ss_contents, true)?;
+    eprintln!("encapsulated to {} key, wrote ciphertext to {ct_out} and shared secret to {ss_out}", params_name(params));
+    Ok(())
+}
+
+fn decaps(dk_path: &str, ct_path: &str, ss_out: &str, format: Format) -> Result<()> {
+    let (params, dk) = load_decaps_key(dk_path)?;
+    let ct = CipherText::from_slice(&decode_data(&read_file(ct_path)?));
+    let ss = MlKem::new(params).decaps(&dk, &ct).map_err(|e| format!("{e} for {}", params_name(params)))?;
+
+    write_file(ss_out, &encode_data(&ss.into_bytes()[..], format)?, true)?;
+    eprintln!("decapsulated with {} key, wrote shared secret to {ss_out}", params_name(params));
+    Ok(())
+}
+
+fn pubkey(dk_path: &str, ek_out: &str, format: Format) -> Result<()> {
+    let (params, dk) = load_decaps_key(dk_path)?;
+    MlKem::new(params).prepare_decaps_key(&dk).map_err(|e| format!("{dk_path}: {e}"))?;
+
+    // dk = dk_pke || ek || H(ek) || z
+    let k = params.k();
+    let dk_bytes = dk.into_bytes();
+    let ek = EncapsKey::from_slice(&dk_bytes[384 * k .. 768 * k + 32]);
+    write_encaps_key(ek_out, params, ek, format)
+}
+
+fn inspect(path: &str) -> Result<()> {
+    let contents = read_file(path)?;
+    if is_pem(&contents) {
+        let pem = std::str::from_utf8(&contents)?;
+        if let Ok((params, ek)) = EncapsKey::from_public_key_pem(pem) {
+            println!("type: encapsulation key (SubjectPublicKeyInfo PEM)");
+            println!("parameter set: {}", params_name(params));
+            println!("length: {} bytes", ek.into_bytes().len());
+            println!("check: ok");
+            return Ok(());
+        }
+        let (params, dk) = DecapsKey::from_pkcs8_pem(pem).map_err(|e| format!("{path}: {e}"))?;
+        let contents = if DecapsKeySeed::from_pkcs8_pem(pem).is_ok() { "seed" } else { "expanded key" };
+        println!("type: decapsulation key (PKCS#8 PEM with {contents})");
+        println!("parameter set: {}", params_name(params));
+        println!("length: {} bytes", dk.into_bytes().len());
+        println!("check: ok");
+        return Ok(());
+    }
+
+    let bytes = decode_data(&contents);
+    let len = bytes.len();
+    // An ML-KEM-1024 encapsulation key and ciphertext have the same length, so both are listed
+    let mut found = false;
+    for params in ALL_PARAMS {
+        let kem = MlKem::new(params);
+        let candidates = [
+            (params.encaps_key_len(), "encapsulation key", kem.prepare_encaps_key(&EncapsKey::from_slice(&bytes)).err()),
+            (params.decaps_key_len(), "decapsulation key", kem.prepare_decaps_key(&DecapsKey::from_slice(&bytes)).err()),
+            (params.ciphertext_len(), "ciphertext", None),
+        ];
+        for (_, kind, check) in candidates.into_iter().filter(|(kind_len, _, _)| *kind_len == len) {
+            if found {
+                println!();
+            }
+            found = true;
+            println!("type: {kind}");
+            println!("parameter set: {}", params_name(params));
+            println!("length: {len} bytes");
+            match check {
+                None => println!("check: ok"),
+                Some(e) => println!("check: failed, {e}"),
+            }
+        }
+    }
+    if !found {
+        let kind = match len {
+            32 => "shared secret",
+            64 => "seed d || z",
+            _ => return Err(format!("{path}: {len} bytes is not an ML-KEM key or ciphertext length").into()),
+        };
+        println!("type: {kind}");
+        println!("length: {len} bytes");
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Keygen { params, seed, ek_out, dk_out, format, dk_contents } => keygen(params, seed, &ek_out, &dk_out, format, dk_contents),
+        Command::Encaps { ek, ct_out, ss_out, format } => encaps(&ek, &ct_out, &ss_out, format),
+        Command::Decaps { dk, ct, ss_out, format } => decaps(&dk, &ct, &ss_out, format),
+        Command::Pubkey { dk, ek_out, format } => pubkey(&dk, &ek_out, format),
+        Command::Inspect { file } => inspect(&file),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("fips203: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
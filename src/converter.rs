@@ -1,44 +1,23 @@
-use crate::Q;
+use crate::{Q, arithmetic::csubq};
 
 // k denotes the length of f_arr
-pub(crate) fn byte_encode_mult(k: usize, f_arr: &Vec<[u16; 256]>, d: u8) -> Vec<u8> {
+pub(crate) fn byte_encode_mult(k: usize, f_arr: &[[i16; 256]], d: u8, output: &mut [u8]) {
     debug_assert!(f_arr.len() == k, "Input array must have k elements");
-    debug_assert!(f_arr[0].len() == 256, "Input elements array must have 256 elements");
-    debug_assert!((1..=12).contains(&d), "Bit-width d must be between 1 and 12");
+    debug_assert!(output.len() == 32 * (d as usize) * k, "Output length should be exactly 32 * d * k");
 
-    let output_len = 32 * (d as usize) * k;
-    let mut output = Vec::with_capacity(output_len);
-    let mut buffer: u64 = 0;
-    let mut bits_in_buffer = 0;
-
-    for i in 0..k {
-        for &val in f_arr[i].iter() {
-            let a = (val & ((1 << d) - 1)) as u64; // ensure only d bits
-            buffer |= a << bits_in_buffer;
-            bits_in_buffer += d;
-    
-            while bits_in_buffer >= 8 {
-                output.push((buffer & 0xFF) as u8);
-                buffer >>= 8;
-                bits_in_buffer -= 8;
-            }
-        }
-    }
-    if bits_in_buffer > 0 {
-        output.push(buffer as u8);
+    for (i, f) in f_arr.iter().enumerate() {
+        byte_encode(f, d, &mut output[32 * (d as usize) * i .. 32 * (d as usize) * (i + 1)]);
     }
-
-    debug_assert!(output.len() == output_len, "Output length should be exactly 32 * d * k");
-    output
 }
 
 // byte_encode for single byte arrays
-pub(crate) fn byte_encode(f_arr: &[u16; 256], d: u8) -> Vec<u8> {
+pub(crate) fn byte_encode(f_arr: &[i16; 256], d: u8, output: &mut [u8]) {
     debug_assert!((1..=12).contains(&d), "Bit-width d must be between 1 and 12");
+    debug_assert!(output.len() == 32 * (d as usize), "Output length should be exactly 32 * d");
 
-    let mut output = Vec::with_capacity(32 * (d as usize));
     let mut buffer: u64 = 0;
     let mut bits_in_buffer = 0;
+    let mut byte_idx = 0;
 
     for &val in f_arr.iter() {
         let a = (val & ((1 << d) - 1)) as u64; // ensure only d bits
@@ -46,25 +25,24 @@ pub(crate) fn byte_encode(f_arr: &[u16; 256], d: u8) -> Vec<u8> {
         bits_in_buffer += d;
 
         while bits_in_buffer >= 8 {
-            output.push((buffer & 0xFF) as u8);
+            output[byte_idx] = (buffer & 0xFF) as u8;
+            byte_idx += 1;
             buffer >>= 8;
             bits_in_buffer -= 8;
         }
     }
-    if bits_in_buffer > 0 {
-        output.push(buffer as u8);
-    }
 
-    debug_assert!(output.len() == 32 * (d as usize), "Output length should be exactly 32 * d");
-    output
+    // 256 * d bits is always a whole number of bytes
+    debug_assert!(bits_in_buffer == 0 && byte_idx == output.len());
 }
 
 // byte_decode used for single byte arrays
-pub(crate) fn byte_decode(bytes: &[u8], d: u8) -> [u16; 256] {
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn byte_decode(bytes: &[u8], d: u8) -> [i16; 256] {
     debug_assert!((1..=12).contains(&d), "Bit-width d must be between 1 and 12");
     debug_assert!(bytes.len() == 32 * (d as usize), "Input byte array must be of length 32 * d");
 
-    let mut output = [0u16; 256];
+    let mut output = [0i16; 256];
     let mut buffer: u64 = 0;
     let mut bits_in_buffer = 0;
     let mut byte_idx = 0;
@@ -76,12 +54,13 @@ pub(crate) fn byte_decode(bytes: &[u8], d: u8) -> [u16; 256] {
             byte_idx += 1;
         }
 
-        let mut val = (buffer & ((1 << d) - 1)) as u16;
+        let mut val = (buffer & ((1 << d) - 1)) as i16;
         buffer >>= d;
         bits_in_buffer -= d;
 
+        // Twelve bits hold values below 2q, which a single conditional subtraction reduces
         if d == 12 {
-            val %= Q;
+            val = csubq(val);
         }
 
         output[i] = val;
@@ -91,21 +70,30 @@ pub(crate) fn byte_decode(bytes: &[u8], d: u8) -> [u16; 256] {
     output
 }
 
-pub(crate) fn compress(d: u8, f: &[u16; 256]) -> [u16; 256] {
-    let mut result = [0u16; 256];
-    let two_pow_d = 1 << d;
-    for i in 0..256 {
-        let scaled = (f[i] as u32 * two_pow_d as u32 + Q as u32 / 2) / Q as u32;
-        result[i] = (scaled % two_pow_d) as u16;
+// ceil(2^36 / q). For any numerator t < q * 2^12, (t * COMPRESS_M) >> 36 equals t / q, as the
+// rounding up adds less than t / 2^36 < 1 / q to the exact quotient.
+const COMPRESS_M: u64 = (1u64 << 36).div_ceil(Q as u64);
+
+// round(2^d / q * x) mod 2^d for canonical x. The division by q would take a time depending on
+// secret coefficients on many CPUs, so it is done with a multiplication and a shift.
+pub(crate) fn compress(d: u8, f: &[i16; 256]) -> [i16; 256] {
+    debug_assert!((1..=12).contains(&d), "Bit-width d must be between 1 and 12");
+
+    let mut result = [0i16; 256];
+    for (r, &x) in result.iter_mut().zip(f) {
+        let t = ((x as u64) << d) + Q as u64 / 2;
+        *r = (((t * COMPRESS_M) >> 36) & ((1 << d) - 1)) as i16;
     }
     result
 }
 
-pub(crate) fn decompress(d: u8, k: &[u16; 256]) -> [u16; 256] {
-    let mut result: [u16; 256] = [0u16; 256];
-    let two_pow_d = 1 << d;
-    for i in 0..256 {
-        result[i] = ((k[i] as u32 * Q as u32 + two_pow_d as u32 / 2) / two_pow_d) as u16;
+// round(q / 2^d * y), dividing by 2^d with a shift
+pub(crate) fn decompress(d: u8, k: &[i16; 256]) -> [i16; 256] {
+    debug_assert!((1..=12).contains(&d), "Bit-width d must be between 1 and 12");
+
+    let mut result = [0i16; 256];
+    for (r, &y) in result.iter_mut().zip(k) {
+        *r = ((y as u32 * Q as u32 + (1 << (d - 1))) >> d) as i16;
     }
     result
 }
@@ -113,14 +101,16 @@ pub(crate) fn decompress(d: u8, k: &[u16; 256]) -> [u16; 256] {
 #[cfg(test)]
 mod tests {
     use super::*; // import everything from the parent module
+    use std::{println, vec, vec::Vec};
 
     #[test]
     fn test_byte_encode_mult_decode_roundtrip() {
         let k: usize = 3;
         let d = 10;
-        let input: Vec<[u16; 256]> = (0..k).map(|block| core::array::from_fn(|i| (block * 256 + i) as u16 % (1 << d))).collect();
+        let input: Vec<[i16; 256]> = (0..k).map(|block| core::array::from_fn(|i| (block * 256 + i) as i16 % (1 << d))).collect();
 
-        let encoded = byte_encode_mult(k, &input, d);
+        let mut encoded = vec![0u8; 32 * (d as usize) * k];
+        byte_encode_mult(k, &input, d, &mut encoded);
         let mut decoded = Vec::with_capacity(k);
         for i in 0..k {
             decoded.push(byte_decode(&encoded[32 * (d as usize) * i .. 32 * (d as usize) * (i + 1)], d));
@@ -131,9 +121,10 @@ mod tests {
     #[test]
     fn test_byte_encode_decode_roundtrip() {
         let d: u8 = 7;
-        let input: [u16; 256] = core::array::from_fn(|i| (i * 9 + i % 17) as u16 % (1 << d));
+        let input: [i16; 256] = core::array::from_fn(|i| (i * 9 + i % 17) as i16 % (1 << d));
 
-        let encoded = byte_encode(&input, d);
+        let mut encoded = [0u8; 32 * 7];
+        byte_encode(&input, d, &mut encoded);
         let decoded = byte_decode(&encoded, d);
         assert_eq!(decoded, input);
     }
@@ -142,22 +133,65 @@ mod tests {
     fn test_byte_encode_decode_d12_modq() {
         let k: usize = 3;
         let d = 10;
-        let input: Vec<[u16; 256]> = (0..k).map(|block| core::array::from_fn(|i| (block * 256 + i) as u16 % (1 << d))).collect();
-        let encoded = byte_encode_mult(k, &input, d);
+        let input: Vec<[i16; 256]> = (0..k).map(|block| core::array::from_fn(|i| (block * 256 + i) as i16 % (1 << d))).collect();
+        let mut encoded = vec![0u8; 32 * (d as usize) * k];
+        byte_encode_mult(k, &input, d, &mut encoded);
         let mut decoded = Vec::with_capacity(k);
         for i in 0..k {
             decoded.push(byte_decode(&encoded[32 * (d as usize) * i .. 32 * (d as usize) * (i + 1)], d));
         }
-        let expected: Vec<[u16; 256]> = input.iter().map(| poly | poly.map(|x| x % 3329)).collect();
+        let expected: Vec<[i16; 256]> = input.iter().map(| poly | poly.map(|x| x % 3329)).collect();
         assert_eq!(decoded, expected);
     }
 
+    // The previous division-based formulas
+    fn compress_reference(d: u8, x: i16) -> i16 {
+        let two_pow_d = 1 << d;
+        let scaled = (x as u32 * two_pow_d + Q as u32 / 2) / Q as u32;
+        (scaled % two_pow_d) as i16
+    }
+
+    fn decompress_reference(d: u8, y: i16) -> i16 {
+        let two_pow_d = 1 << d;
+        ((y as u32 * Q as u32 + two_pow_d / 2) / two_pow_d) as i16
+    }
+
+    #[test]
+    fn test_compress_matches_division() {
+        let inputs: Vec<i16> = (0..Q).collect();
+        for d in 1..=12 {
+            for chunk in inputs.chunks(256) {
+                let mut f = [0i16; 256];
+                f[..chunk.len()].copy_from_slice(chunk);
+                let compressed = compress(d, &f);
+                for (&x, &c) in f.iter().zip(&compressed) {
+                    assert_eq!(c, compress_reference(d, x), "d = {d}, x = {x}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decompress_matches_division() {
+        for d in 1..=12 {
+            let inputs: Vec<i16> = (0..1 << d).collect();
+            for chunk in inputs.chunks(256) {
+                let mut y = [0i16; 256];
+                y[..chunk.len()].copy_from_slice(chunk);
+                let decompressed = decompress(d, &y);
+                for (&y, &r) in y.iter().zip(&decompressed) {
+                    assert_eq!(r, decompress_reference(d, y), "d = {d}, y = {y}");
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_compress_decompress_roundtrip() {
         let d = 12;
-        let all_values: Vec<u16> = (0..3329).collect();
+        let all_values: Vec<i16> = (0..3329).collect();
         for chunk in all_values.chunks(256) {
-            let mut input = [0u16; 256];
+            let mut input = [0i16; 256];
             for (i, &val) in chunk.iter().enumerate() {
                 input[i] = val;
             }
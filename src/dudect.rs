@@ -0,0 +1,235 @@
+// Statistical timing tests in the style of dudect (Reparaz, Balasch and Verbauwhede, "Dude, is my
+// code constant time?", DATE 2017)
+//
+// Each test times an operation on inputs of two classes, drawn in random order, and compares the two
+// timing distributions with Welch's t-test. The test runs on all measurements and on measurements
+// cropped at 100 percentiles, which removes the long tail that interrupts add. |t| above 4.5 hints at a
+// difference between the classes and |t| above 10 is a clear leak. The tests need many samples and a
+// quiet machine, so they are ignored by default:
+//
+//     taskset -c 1 cargo test --release --lib dudect -- --ignored --nocapture --test-threads 1
+//
+// DUDECT_SAMPLES sets the number of measurements per test.
+
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::{_mm_lfence, _rdtsc};
+use core::hint::black_box;
+use std::{env, eprintln, vec, vec::Vec};
+
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+
+use crate::{MAX_CIPHERTEXT_LEN, MlKem768, ParameterSet, Q, converter::{byte_decode, compress}, k_pke::{PkeDecryptionKey, pke_decrypt_prepared},
+ml_kem_internal::{PreparedDk, PreparedEk, ml_kem_decaps_prepared, ml_kem_encaps_prepared, ml_kem_keygen_internal}};
+
+// dudect's threshold for a definite leak
+const T_LEAK: f64 = 10.0;
+const CROPS: usize = 100;
+const BATCH: usize = 10_000;
+
+// Welford's running mean and variance of the timings of each class
+#[derive(Clone, Default)]
+struct TTest {
+    n: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl TTest {
+    fn push(&mut self, class: usize, x: f64) {
+        self.n[class] += 1.0;
+        let delta = x - self.mean[class];
+        self.mean[class] += delta / self.n[class];
+        self.m2[class] += delta * (x - self.mean[class]);
+    }
+
+    fn samples(&self) -> f64 {
+        self.n[0].min(self.n[1])
+    }
+
+    // Welch's t-statistic
+    fn t(&self) -> f64 {
+        let var = |class: usize| self.m2[class] / (self.n[class] - 1.0);
+        (self.mean[0] - self.mean[1]) / (var(0) / self.n[0] + var(1) / self.n[1]).sqrt()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn cycles() -> u64 {
+    // The fences keep the timed instructions from moving across the counter reads
+    // SAFETY: SSE2 and rdtsc are available on every x86_64 CPU
+    unsafe {
+        _mm_lfence();
+        let t = _rdtsc();
+        _mm_lfence();
+        t
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn cycles() -> u64 {
+    use std::{sync::OnceLock, time::Instant};
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+fn samples(default: usize) -> usize {
+    env::var("DUDECT_SAMPLES").ok().and_then(|s| s.parse().ok()).unwrap_or(default)
+}
+
+// Times op on inputs of class 0 and class 1, reports the t-statistics and returns the largest |t|.
+// The first batch warms up and sets the crop thresholds, it is not part of the statistics.
+fn measure<T>(name: &str, samples: usize, mut input: impl FnMut(usize, &mut ChaCha20Rng) -> T, mut op: impl FnMut(&T)) -> f64 {
+    let mut rng = ChaCha20Rng::seed_from_u64(23);
+    let mut tests = vec![TTest::default(); CROPS + 1];
+    let mut thresholds = Vec::new();
+
+    for batch in 0..=samples.div_ceil(BATCH) {
+        let classes: Vec<usize> = (0..BATCH).map(|_| (rng.next_u32() & 1) as usize).collect();
+        let inputs: Vec<T> = classes.iter().map(|&class| input(class, &mut rng)).collect();
+        let mut times = Vec::with_capacity(BATCH);
+        for x in &inputs {
+            let start = cycles();
+            op(black_box(x));
+            times.push(cycles().wrapping_sub(start));
+        }
+
+        if batch == 0 {
+            times.sort_unstable();
+            // The percentiles 1 - 0.5^(10(i + 1) / CROPS), denser towards the fast end as in dudect
+            thresholds = (0..CROPS).map(|i| {
+                let p = 1.0 - 0.5f64.powf(10.0 * (i + 1) as f64 / CROPS as f64);
+                times[(p * BATCH as f64) as usize]
+            }).collect();
+            continue;
+        }
+
+        for (&class, &time) in classes.iter().zip(&times) {
+            tests[0].push(class, time as f64);
+            for (test, &threshold) in tests[1..].iter_mut().zip(&thresholds) {
+                if time < threshold {
+                    test.push(class, time as f64);
+                }
+            }
+        }
+    }
+
+    // Crops holding too few measurements give meaningless statistics
+    let (crop, max_t) = tests.iter().enumerate()
+        .filter(|(_, test)| test.samples() > 1000.0)
+        .map(|(crop, test)| (crop, test.t().abs()))
+        .fold((0, 0.0), |max, (crop, t)| if t > max.1 { (crop, t) } else { max });
+    let verdict = if max_t > T_LEAK { "leak" } else if max_t > 4.5 { "possible leak" } else { "ok" };
+    eprintln!("{name:<24} {:>9} measurements  t = {:>+7.2}  max |t| = {max_t:>6.2} (crop {crop:>3})  {verdict}",
+        tests[0].n[0] + tests[0].n[1], tests[0].t());
+    max_t
+}
+
+fn random_poly(rng: &mut ChaCha20Rng) -> [i16; 256] {
+    core::array::from_fn(|_| (rng.next_u32() % Q as u32) as i16)
+}
+
+fn key_pair() -> (Vec<u8>, Vec<u8>) {
+    let k = MlKem768::K;
+    let (mut ek, mut dk) = (vec![0u8; 384 * k + 32], vec![0u8; 768 * k + 96]);
+    ml_kem_keygen_internal(k, MlKem768::ETA1, &[1u8; 32], &[2u8; 32], &mut ek, &mut dk);
+    (ek, dk)
+}
+
+fn ciphertext_len() -> usize {
+    32 * (MlKem768::DU as usize * MlKem768::K + MlKem768::DV as usize)
+}
+
+// Valid ciphertexts against random ones, which decapsulation implicitly rejects
+#[test]
+#[ignore]
+fn dudect_decaps() {
+    let (k, eta1, eta2, du, dv) = (MlKem768::K, MlKem768::ETA1, MlKem768::ETA2, MlKem768::DU, MlKem768::DV);
+    let (ek, dk) = key_pair();
+    let (ek, dk) = (PreparedEk::new(k, &ek), PreparedDk::new(k, &dk));
+
+    let t = measure("decaps", samples(100_000), |class, rng| {
+        let mut c = [0u8; MAX_CIPHERTEXT_LEN];
+        if class == 0 {
+            let mut m = [0u8; 32];
+            rng.fill_bytes(&mut m);
+            ml_kem_encaps_prepared(k, eta1, eta2, du, dv, &ek, &m, &mut c[..ciphertext_len()]);
+        } else {
+            rng.fill_bytes(&mut c[..ciphertext_len()]);
+        }
+        c
+    }, |c| {
+        black_box(ml_kem_decaps_prepared(k, eta1, eta2, du, dv, &dk, &c[..ciphertext_len()]));
+    });
+    assert!(t < T_LEAK);
+}
+
+// A fixed ciphertext against random ones, giving a fixed against a random w
+#[test]
+#[ignore]
+fn dudect_pke_decrypt() {
+    let (k, du, dv) = (MlKem768::K, MlKem768::DU, MlKem768::DV);
+    let (_, dk) = key_pair();
+    let dk = PkeDecryptionKey::new(k, &dk[..384 * k]);
+    let mut fixed = [0u8; MAX_CIPHERTEXT_LEN];
+    ChaCha20Rng::seed_from_u64(0).fill_bytes(&mut fixed[..ciphertext_len()]);
+
+    let t = measure("pke_decrypt", samples(200_000), |class, rng| {
+        let mut c = fixed;
+        if class == 1 {
+            rng.fill_bytes(&mut c[..ciphertext_len()]);
+        }
+        c
+    }, |c| {
+        black_box(pke_decrypt_prepared(k, du, dv, &dk, &c[..ciphertext_len()]));
+    });
+    assert!(t < T_LEAK);
+}
+
+// The zero polynomial against random ones, for each d the parameter sets compress to
+#[test]
+#[ignore]
+fn dudect_compress() {
+    let mut max_t: f64 = 0.0;
+    for d in [1, 4, 5, 10, 11] {
+        max_t = max_t.max(measure(&std::format!("compress d = {d}"), samples(1_000_000), |class, rng| {
+            if class == 0 { [0i16; 256] } else { random_poly(rng) }
+        }, |f| {
+            black_box(compress(d, f));
+        }));
+    }
+    assert!(max_t < T_LEAK);
+}
+
+// All zero bytes against random ones, for each d in the ciphertexts and keys
+#[test]
+#[ignore]
+fn dudect_byte_decode() {
+    let mut max_t: f64 = 0.0;
+    for d in [1, 4, 5, 10, 11, 12] {
+        max_t = max_t.max(measure(&std::format!("byte_decode d = {d}"), samples(1_000_000), |class, rng| {
+            let mut bytes = [0u8; 32 * 12];
+            if class == 1 {
+                rng.fill_bytes(&mut bytes[..32 * d as usize]);
+            }
+            bytes
+        }, |bytes| {
+            black_box(byte_decode(&bytes[..32 * d as usize], d));
+        }));
+    }
+    assert!(max_t < T_LEAK);
+}
+
+#[test]
+fn test_welch_t() {
+    let mut rng = ChaCha20Rng::seed_from_u64(23);
+    let (mut same, mut shifted) = (TTest::default(), TTest::default());
+    for _ in 0..100_000 {
+        let class = (rng.next_u32() & 1) as usize;
+        let x = (rng.next_u32() % 1000) as f64;
+        same.push(class, x);
+        shifted.push(class, x + 50.0 * class as f64);
+    }
+    assert!(same.t().abs() < 4.5);
+    assert!(shifted.t().abs() > T_LEAK);
+}
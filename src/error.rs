@@ -0,0 +1,48 @@
+use core::fmt;
+
+/// Errors returned by the ML-KEM operations.
+///
+/// New variants may be added in minor releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The encapsulation key failed the type or modulus check (FIPS 203 §7.2).
+    InvalidEncapsKey,
+    /// The decapsulation key failed the type or hash check (FIPS 203 §7.3).
+    InvalidDecapsKey,
+    /// The ciphertext does not have the length required by the parameter set (FIPS 203 §7.3).
+    InvalidCipherText,
+    /// The random number generator failed to produce bytes.
+    RandomnessFailure,
+    /// The DER or PEM document is malformed or names a different algorithm.
+    InvalidEncoding,
+    /// The AEAD ciphertext of an HPKE message or sealed box failed authentication.
+    OpenFailure,
+    /// The HPKE context has used up its sequence numbers.
+    MessageLimitReached,
+    /// The PSK and PSK ID must both be empty in base mode and both be set in PSK mode.
+    InvalidPsk,
+    /// The HPKE context uses the export-only AEAD and cannot seal or open messages.
+    ExportOnly,
+    /// The requested HPKE export is longer than 255 times the KDF output length.
+    InvalidExportLength,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidEncapsKey => f.write_str("invalid encapsulation key"),
+            Error::InvalidDecapsKey => f.write_str("invalid decapsulation key"),
+            Error::InvalidCipherText => f.write_str("invalid ciphertext"),
+            Error::RandomnessFailure => f.write_str("random number generator failure"),
+            Error::InvalidEncoding => f.write_str("invalid key encoding"),
+            Error::OpenFailure => f.write_str("decryption failed"),
+            Error::MessageLimitReached => f.write_str("message limit reached"),
+            Error::InvalidPsk => f.write_str("inconsistent PSK inputs"),
+            Error::ExportOnly => f.write_str("export-only context"),
+            Error::InvalidExportLength => f.write_str("export length too large"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
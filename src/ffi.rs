@@ -0,0 +1,279 @@
+//! C API for the shared library, declared in `include/fips203.h`.
+//!
+//! Keys, ciphertexts and shared secrets are caller owned byte buffers. Every function returns
+//! `FIPS203_OK` or one of the negative `FIPS203_ERR_*` status codes, and key and ciphertext
+//! buffers must be exactly the length returned by the size queries for the parameter set.
+
+use core::{ffi::{c_char, c_int}, slice};
+
+use crate::{MlKem512, MlKem768, MlKem1024, params::{ByteArray, ParameterSet}, typed::{CipherText, DecapsKey, EncapsKey, MlKem}, types::DecapsKeySeed};
+
+/// ML-KEM-512 (security category 1).
+pub const FIPS203_ML_KEM_512: u32 = 512;
+/// ML-KEM-768 (security category 3).
+pub const FIPS203_ML_KEM_768: u32 = 768;
+/// ML-KEM-1024 (security category 5).
+pub const FIPS203_ML_KEM_1024: u32 = 1024;
+
+/// Length of the shared secret for every parameter set.
+pub const FIPS203_SHARED_SECRET_LEN: usize = 32;
+/// Length of the key generation seed `d || z` for every parameter set.
+pub const FIPS203_SEED_LEN: usize = 64;
+
+/// The operation succeeded.
+pub const FIPS203_OK: c_int = 0;
+/// A required pointer was null.
+pub const FIPS203_ERR_NULL_POINTER: c_int = -1;
+/// The parameter set is not one of the `FIPS203_ML_KEM_*` values.
+pub const FIPS203_ERR_INVALID_PARAMS: c_int = -2;
+/// An output buffer does not have the length required by the parameter set.
+pub const FIPS203_ERR_BUFFER_LENGTH: c_int = -3;
+/// The encapsulation key failed the FIPS 203 §7.2 checks.
+pub const FIPS203_ERR_INVALID_ENCAPS_KEY: c_int = -4;
+/// The decapsulation key failed the FIPS 203 §7.3 checks.
+pub const FIPS203_ERR_INVALID_DECAPS_KEY: c_int = -5;
+/// The ciphertext does not have the length required by the parameter set.
+pub const FIPS203_ERR_INVALID_CIPHERTEXT: c_int = -6;
+/// The operating system random number generator failed.
+pub const FIPS203_ERR_RANDOMNESS: c_int = -7;
+
+// Calls the generic function with the parameter set selected at runtime
+macro_rules! with_params {
+    ($params:expr, $f:ident($($arg:expr),*)) => {
+        match $params {
+            FIPS203_ML_KEM_512 => $f::<MlKem512>($($arg),*),
+            FIPS203_ML_KEM_768 => $f::<MlKem768>($($arg),*),
+            FIPS203_ML_KEM_1024 => $f::<MlKem1024>($($arg),*),
+            _ => FIPS203_ERR_INVALID_PARAMS,
+        }
+    };
+}
+
+// None for a null pointer, otherwise the caller guarantees ptr is valid for len bytes
+unsafe fn input<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    (!ptr.is_null()).then(|| unsafe { slice::from_raw_parts(ptr, len) })
+}
+
+unsafe fn output<'a>(ptr: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    (!ptr.is_null()).then(|| unsafe { slice::from_raw_parts_mut(ptr, len) })
+}
+
+// (encapsulation key, decapsulation key, ciphertext) lengths, all 0 for an unknown parameter set
+fn params_lens(params: u32) -> (usize, usize, usize) {
+    fn lens<P: ParameterSet>() -> (usize, usize, usize) {
+        (P::EncapsKeyBytes::LEN, P::DecapsKeyBytes::LEN, P::CipherTextBytes::LEN)
+    }
+    match params {
+        FIPS203_ML_KEM_512 => lens::<MlKem512>(),
+        FIPS203_ML_KEM_768 => lens::<MlKem768>(),
+        FIPS203_ML_KEM_1024 => lens::<MlKem1024>(),
+        _ => (0, 0, 0),
+    }
+}
+
+/// Returns the encapsulation key length for the parameter set, or 0 if it is unknown.
+#[unsafe(no_mangle)]
+pub extern "C" fn fips203_encaps_key_len(params: u32) -> usize {
+    params_lens(params).0
+}
+
+/// Returns the decapsulation key length for the parameter set, or 0 if it is unknown.
+#[unsafe(no_mangle)]
+pub extern "C" fn fips203_decaps_key_len(params: u32) -> usize {
+    params_lens(params).1
+}
+
+/// Returns the ciphertext length for the parameter set, or 0 if it is unknown.
+#[unsafe(no_mangle)]
+pub extern "C" fn fips203_ciphertext_len(params: u32) -> usize {
+    params_lens(params).2
+}
+
+/// Returns a static description of the status code.
+#[unsafe(no_mangle)]
+pub extern "C" fn fips203_status_string(status: c_int) -> *const c_char {
+    let description = match status {
+        FIPS203_OK => c"success",
+        FIPS203_ERR_NULL_POINTER => c"null pointer",
+        FIPS203_ERR_INVALID_PARAMS => c"unknown parameter set",
+        FIPS203_ERR_BUFFER_LENGTH => c"output buffer has the wrong length",
+        FIPS203_ERR_INVALID_ENCAPS_KEY => c"invalid encapsulation key",
+        FIPS203_ERR_INVALID_DECAPS_KEY => c"invalid decapsulation key",
+        FIPS203_ERR_INVALID_CIPHERTEXT => c"invalid ciphertext",
+        FIPS203_ERR_RANDOMNESS => c"random number generator failure",
+        _ => c"unknown status",
+    };
+    description.as_ptr()
+}
+
+fn keygen_from_seed<P: ParameterSet>(seed: &DecapsKeySeed, ek: &mut [u8], dk: &mut [u8]) -> c_int {
+    if ek.len() != P::EncapsKeyBytes::LEN || dk.len() != P::DecapsKeyBytes::LEN {
+        return FIPS203_ERR_BUFFER_LENGTH;
+    }
+    let (ek_p, dk_p) = MlKem::<P>::new().keygen_from_seed(seed);
+    ek.copy_from_slice(ek_p.as_bytes().as_ref());
+    dk.copy_from_slice(dk_p.as_bytes().as_ref());
+    FIPS203_OK
+}
+
+fn encaps<P: ParameterSet>(ek: &[u8], ct: &mut [u8], shared_secret: &mut [u8]) -> c_int {
+    if ct.len() != P::CipherTextBytes::LEN {
+        return FIPS203_ERR_BUFFER_LENGTH;
+    }
+    let Ok(ek) = EncapsKey::<P>::from_slice(ek) else {
+        return FIPS203_ERR_INVALID_ENCAPS_KEY;
+    };
+    let Ok((ssk, c)) = MlKem::<P>::new().encaps(&ek) else {
+        return FIPS203_ERR_RANDOMNESS;
+    };
+    ct.copy_from_slice(c.as_bytes().as_ref());
+    shared_secret.copy_from_slice(&ssk.0);
+    FIPS203_OK
+}
+
+fn decaps<P: ParameterSet>(dk: &[u8], ct: &[u8], shared_secret: &mut [u8]) -> c_int {
+    let Ok(dk) = DecapsKey::<P>::from_slice(dk) else {
+        return FIPS203_ERR_INVALID_DECAPS_KEY;
+    };
+    let Ok(c) = CipherText::<P>::from_slice(ct) else {
+        return FIPS203_ERR_INVALID_CIPHERTEXT;
+    };
+    let ssk = MlKem::<P>::new().decaps(&dk, &c);
+    shared_secret.copy_from_slice(&ssk.0);
+    FIPS203_OK
+}
+
+/// Generates a key pair with the operating system random number generator.
+///
+/// # Safety
+///
+/// `ek` must be valid for writes of `ek_len` bytes and `dk` for writes of `dk_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fips203_keygen(params: u32, ek: *mut u8, ek_len: usize, dk: *mut u8, dk_len: usize) -> c_int {
+    let Ok(seed) = DecapsKeySeed::generate() else {
+        return FIPS203_ERR_RANDOMNESS;
+    };
+    unsafe { fips203_keygen_from_seed(params, seed.0.as_ptr(), ek, ek_len, dk, dk_len) }
+}
+
+/// Deterministically expands the `FIPS203_SEED_LEN` byte seed `d || z` into a key pair.
+///
+/// # Safety
+///
+/// `seed` must be valid for reads of `FIPS203_SEED_LEN` bytes, `ek` for writes of `ek_len` bytes
+/// and `dk` for writes of `dk_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fips203_keygen_from_seed(params: u32, seed: *const u8, ek: *mut u8, ek_len: usize, dk: *mut u8, dk_len: usize) -> c_int {
+    let buffers = unsafe { (input(seed, FIPS203_SEED_LEN), output(ek, ek_len), output(dk, dk_len)) };
+    let (seed, ek, dk) = match buffers {
+        (Some(seed), Some(ek), Some(dk)) => (seed, ek, dk),
+        _ => return FIPS203_ERR_NULL_POINTER,
+    };
+    let seed = DecapsKeySeed(seed.try_into().unwrap());
+    with_params!(params, keygen_from_seed(&seed, ek, dk))
+}
+
+/// Encapsulates a fresh shared secret to the encapsulation key.
+///
+/// # Safety
+///
+/// `ek` must be valid for reads of `ek_len` bytes, `ct` for writes of `ct_len` bytes and
+/// `shared_secret` for writes of `FIPS203_SHARED_SECRET_LEN` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fips203_encaps(params: u32, ek: *const u8, ek_len: usize, ct: *mut u8, ct_len: usize, shared_secret: *mut u8) -> c_int {
+    let buffers = unsafe { (input(ek, ek_len), output(ct, ct_len), output(shared_secret, FIPS203_SHARED_SECRET_LEN)) };
+    let (ek, ct, shared_secret) = match buffers {
+        (Some(ek), Some(ct), Some(shared_secret)) => (ek, ct, shared_secret),
+        _ => return FIPS203_ERR_NULL_POINTER,
+    };
+    with_params!(params, encaps(ek, ct, shared_secret))
+}
+
+/// Decapsulates the shared secret from the ciphertext.
+///
+/// A ciphertext of the right length always yields a shared secret, which is the implicit
+/// rejection value if the ciphertext was not produced for this key.
+///
+/// # Safety
+///
+/// `dk` must be valid for reads of `dk_len` bytes, `ct` for reads of `ct_len` bytes and
+/// `shared_secret` for writes of `FIPS203_SHARED_SECRET_LEN` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fips203_decaps(params: u32, dk: *const u8, dk_len: usize, ct: *const u8, ct_len: usize, shared_secret: *mut u8) -> c_int {
+    let buffers = unsafe { (input(dk, dk_len), input(ct, ct_len), output(shared_secret, FIPS203_SHARED_SECRET_LEN)) };
+    let (dk, ct, shared_secret) = match buffers {
+        (Some(dk), Some(ct), Some(shared_secret)) => (dk, ct, shared_secret),
+        _ => return FIPS203_ERR_NULL_POINTER,
+    };
+    with_params!(params, decaps(dk, ct, shared_secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{ffi::CStr, ptr};
+    use std::vec;
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(fips203_encaps_key_len(FIPS203_ML_KEM_512), 800);
+        assert_eq!(fips203_decaps_key_len(FIPS203_ML_KEM_768), 2400);
+        assert_eq!(fips203_ciphertext_len(FIPS203_ML_KEM_1024), 1568);
+        assert_eq!(fips203_encaps_key_len(1), 0);
+        assert_eq!(fips203_ciphertext_len(0), 0);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for params in [FIPS203_ML_KEM_512, FIPS203_ML_KEM_768, FIPS203_ML_KEM_1024] {
+            let mut ek = vec![0u8; fips203_encaps_key_len(params)];
+            let mut dk = vec![0u8; fips203_decaps_key_len(params)];
+            let mut ct = vec![0u8; fips203_ciphertext_len(params)];
+            let mut ss_enc = [0u8; FIPS203_SHARED_SECRET_LEN];
+            let mut ss_dec = [0u8; FIPS203_SHARED_SECRET_LEN];
+            unsafe {
+                assert_eq!(fips203_keygen(params, ek.as_mut_ptr(), ek.len(), dk.as_mut_ptr(), dk.len()), FIPS203_OK);
+                assert_eq!(fips203_encaps(params, ek.as_ptr(), ek.len(), ct.as_mut_ptr(), ct.len(), ss_enc.as_mut_ptr()), FIPS203_OK);
+                assert_eq!(fips203_decaps(params, dk.as_ptr(), dk.len(), ct.as_ptr(), ct.len(), ss_dec.as_mut_ptr()), FIPS203_OK);
+            }
+            assert_eq!(ss_enc, ss_dec);
+        }
+    }
+
+    #[test]
+    fn test_keygen_from_seed_matches_typed() {
+        let seed = [5u8; FIPS203_SEED_LEN];
+        let (ek_typed, dk_typed) = MlKem::<MlKem768>::new().keygen_from_seed(&DecapsKeySeed(seed));
+        let mut ek = [0u8; 1184];
+        let mut dk = [0u8; 2400];
+        let status = unsafe { fips203_keygen_from_seed(FIPS203_ML_KEM_768, seed.as_ptr(), ek.as_mut_ptr(), ek.len(), dk.as_mut_ptr(), dk.len()) };
+        assert_eq!(status, FIPS203_OK);
+        assert_eq!(&ek, ek_typed.as_bytes());
+        assert_eq!(&dk, dk_typed.as_bytes());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let seed = [5u8; FIPS203_SEED_LEN];
+        let mut ek = [0u8; 800];
+        let mut dk = [0u8; 1632];
+        let mut ct = [0u8; 768];
+        let mut ss = [0u8; FIPS203_SHARED_SECRET_LEN];
+        unsafe {
+            assert_eq!(fips203_keygen_from_seed(FIPS203_ML_KEM_512, ptr::null(), ek.as_mut_ptr(), ek.len(), dk.as_mut_ptr(), dk.len()), FIPS203_ERR_NULL_POINTER);
+            assert_eq!(fips203_keygen_from_seed(999, seed.as_ptr(), ek.as_mut_ptr(), ek.len(), dk.as_mut_ptr(), dk.len()), FIPS203_ERR_INVALID_PARAMS);
+            assert_eq!(fips203_keygen_from_seed(FIPS203_ML_KEM_768, seed.as_ptr(), ek.as_mut_ptr(), ek.len(), dk.as_mut_ptr(), dk.len()), FIPS203_ERR_BUFFER_LENGTH);
+            assert_eq!(fips203_keygen_from_seed(FIPS203_ML_KEM_512, seed.as_ptr(), ek.as_mut_ptr(), ek.len(), dk.as_mut_ptr(), dk.len()), FIPS203_OK);
+
+            assert_eq!(fips203_encaps(FIPS203_ML_KEM_512, ek.as_ptr(), ek.len() - 1, ct.as_mut_ptr(), ct.len(), ss.as_mut_ptr()), FIPS203_ERR_INVALID_ENCAPS_KEY);
+            assert_eq!(fips203_encaps(FIPS203_ML_KEM_512, ek.as_ptr(), ek.len(), ct.as_mut_ptr(), ct.len(), ptr::null_mut()), FIPS203_ERR_NULL_POINTER);
+            assert_eq!(fips203_decaps(FIPS203_ML_KEM_512, dk.as_ptr(), dk.len(), ct.as_ptr(), ct.len() - 1, ss.as_mut_ptr()), FIPS203_ERR_INVALID_CIPHERTEXT);
+            // Corrupting H(ek) fails the decapsulation key check
+            dk[768 * 2 + 32] ^= 1;
+            assert_eq!(fips203_decaps(FIPS203_ML_KEM_512, dk.as_ptr(), dk.len(), ct.as_ptr(), ct.len(), ss.as_mut_ptr()), FIPS203_ERR_INVALID_DECAPS_KEY);
+        }
+
+        let description = unsafe { CStr::from_ptr(fips203_status_string(FIPS203_ERR_INVALID_DECAPS_KEY)) };
+        assert_eq!(description.to_str().unwrap(), "invalid decapsulation key");
+    }
+}
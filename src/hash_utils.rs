@@ -1,15 +1,18 @@
+use crate::keccak::{SHAKE256_RATE, Shake256X4};
+use zeroize::{Zeroize, Zeroizing};
 use sha3::{Digest, Sha3_256, Sha3_512, Shake256, digest::{Update, ExtendableOutput, XofReader}};
 
 pub(crate) fn g(seed: &[u8], extra: &[u8]) -> ([u8; 32], [u8; 32]) {
     let mut hasher = Sha3_512::new();
     Digest::update(&mut hasher, seed);
     Digest::update(&mut hasher, extra);
-    let result = hasher.finalize();
+    let mut result = hasher.finalize();
 
     let mut a = [0u8; 32];
     let mut b = [0u8; 32];
     a.copy_from_slice(&result[..32]);
     b.copy_from_slice(&result[32..]);
+    result.as_mut_slice().zeroize();
 
     (a, b)
 }
@@ -20,7 +23,7 @@ pub(crate) fn g_33(seed: &[u8], k: u8) -> ([u8; 32], [u8; 32]) {
 
 pub(crate) fn h(s: &[u8]) -> [u8; 32] {
     let mut hasher = Sha3_256::new();
-    Digest::update(&mut hasher, &s);
+    Digest::update(&mut hasher, s);
     hasher.finalize().into()
 }
 
@@ -38,7 +41,11 @@ pub(crate) fn j(s: &[u8], c: &[u8]) -> [u8; 32] {
     output
 }
 
-pub(crate) fn prf(eta: u8, s: &[u8; 32], b: u8) -> Vec<u8> {
+// Output buffer large enough for the PRF with eta = 3
+pub(crate) const PRF_MAX_LEN: usize = 64 * 3;
+
+// Fills the first 64 * eta bytes of output and returns them
+pub(crate) fn prf<'a>(eta: u8, s: &[u8; 32], b: u8, output: &'a mut [u8; PRF_MAX_LEN]) -> &'a [u8] {
     debug_assert!(eta == 2 || eta == 3, "eta must be 2 or 3");
 
     let mut hasher = Shake256::default();
@@ -47,8 +54,59 @@ pub(crate) fn prf(eta: u8, s: &[u8; 32], b: u8) -> Vec<u8> {
 
     let mut xof = hasher.finalize_xof();
 
-    let mut output = vec![0u8; 64 * eta as usize];
-    xof.read(&mut output);
+    let output = &mut output[..64 * eta as usize];
+    xof.read(output);
 
     output
 }
+
+// Four prf calls on the same seed, squeezing their SHAKE256 streams together. Fills the first
+// 64 * eta bytes of each output and returns them.
+pub(crate) fn prf_x4<'a>(eta: [u8; 4], s: &[u8; 32], b: [u8; 4], outputs: &'a mut [[u8; PRF_MAX_LEN]; 4]) -> [&'a [u8]; 4] {
+    debug_assert!(eta.iter().all(|&eta| eta == 2 || eta == 3), "eta must be 2 or 3");
+
+    let inputs = Zeroizing::new(b.map(|b| {
+        let mut input = [0u8; 33];
+        input[..32].copy_from_slice(s);
+        input[32] = b;
+        input
+    }));
+    let mut xof = Shake256X4::new(inputs.each_ref().map(|input| input.as_slice()));
+
+    // Every lane squeezes as many blocks as the longest output needs
+    let len = 64 * *eta.iter().max().unwrap() as usize;
+    let mut blocks = Zeroizing::new([[0u8; SHAKE256_RATE]; 4]);
+    for start in (0..len).step_by(SHAKE256_RATE) {
+        xof.squeeze_block(&mut blocks);
+        let end = len.min(start + SHAKE256_RATE);
+        for (output, block) in outputs.iter_mut().zip(blocks.iter()) {
+            output[start..end].copy_from_slice(&block[..end - start]);
+        }
+    }
+
+    let outputs: &'a [[u8; PRF_MAX_LEN]; 4] = outputs;
+    core::array::from_fn(|lane| &outputs[lane][..64 * eta[lane] as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{RngCore, SeedableRng};
+
+    #[test]
+    fn test_prf_x4_matches_prf() {
+        let mut rng = ChaCha20Rng::seed_from_u64(20);
+        for _ in 0..50 {
+            let mut s = [0u8; 32];
+            rng.fill_bytes(&mut s);
+            let eta = core::array::from_fn(|_| 2 + (rng.next_u32() % 2) as u8);
+            let b = core::array::from_fn(|_| rng.next_u32() as u8);
+            let mut outputs = [[0u8; PRF_MAX_LEN]; 4];
+            let mut expected = [0u8; PRF_MAX_LEN];
+            for (lane, output) in prf_x4(eta, &s, b, &mut outputs).into_iter().enumerate() {
+                assert_eq!(output, prf(eta[lane], &s, b[lane], &mut expected));
+            }
+        }
+    }
+}
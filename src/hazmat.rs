@@ -0,0 +1,317 @@
+//! K-PKE and the internal algorithms of FIPS 203, for research and non-standard protocols.
+//!
+//! These are the building blocks ML-KEM is made of. K-PKE on its own is only IND-CPA secure, and
+//! nothing in this module checks keys the way FIPS 203 §7 requires, so use [`MlKem`](crate::MlKem)
+//! or [`typed::MlKem`](crate::typed::MlKem) unless a protocol needs the pieces directly.
+//!
+//! This module is not covered by the semver guarantees of the crate. Its types and signatures may
+//! change in any release.
+
+use core::ops::{Add, Mul, Sub};
+
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::{Error, arithmetic::reduce_u16, converter, k_pke::{self, PkeDecryptionKey, PkeEncryptionKey}, ntt::{self, multiply_ntts, poly_add, poly_sub}, params::ParameterSet, sample};
+
+/// A polynomial of R_q, with coefficients in [0, q).
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct Poly([i16; 256]);
+
+/// A polynomial of T_q, the NTT representation of R_q, with coefficients in [0, q).
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct NttPoly([i16; 256]);
+
+// Both representations are 256 coefficients modulo q that add and subtract the same way
+macro_rules! impl_poly {
+    ($poly:ident) => {
+        impl $poly {
+            pub fn zero() -> Self {
+                $poly([0; 256])
+            }
+
+            /// Reduces each coefficient modulo q.
+            pub fn from_coefficients(coefficients: &[u16; 256]) -> Self {
+                $poly(coefficients.map(reduce_u16))
+            }
+
+            pub fn coefficients(&self) -> [u16; 256] {
+                self.0.map(|c| c as u16)
+            }
+        }
+
+        impl Add for &$poly {
+            type Output = $poly;
+
+            fn add(self, rhs: Self) -> $poly {
+                let mut sum = self.clone();
+                poly_add(&mut sum.0, &rhs.0);
+                sum
+            }
+        }
+
+        impl Sub for &$poly {
+            type Output = $poly;
+
+            fn sub(self, rhs: Self) -> $poly {
+                $poly(poly_sub(&self.0, &rhs.0))
+            }
+        }
+    };
+}
+
+impl_poly!(Poly);
+impl_poly!(NttPoly);
+
+/// MultiplyNTTs (FIPS 203 Algorithm 11), the product in T_q.
+impl Mul for &NttPoly {
+    type Output = NttPoly;
+
+    fn mul(self, rhs: Self) -> NttPoly {
+        NttPoly(multiply_ntts(&self.0, &rhs.0))
+    }
+}
+
+fn check_d(d: u8) {
+    assert!((1..=12).contains(&d), "d must be between 1 and 12");
+}
+
+/// K-PKE.KeyGen (FIPS 203 Algorithm 13) for the parameter set `P`.
+///
+/// `ek_pke` must be 384k + 32 bytes and `dk_pke` 384k bytes.
+pub fn pke_key_gen<P: ParameterSet>(d: &[u8; 32], ek_pke: &mut [u8], dk_pke: &mut [u8]) -> Result<(), Error> {
+    if ek_pke.len() != 384 * P::K + 32 {
+        return Err(Error::InvalidEncapsKey);
+    }
+    if dk_pke.len() != 384 * P::K {
+        return Err(Error::InvalidDecapsKey);
+    }
+
+    k_pke::pke_key_gen(P::K, P::ETA1, d, ek_pke, dk_pke);
+    Ok(())
+}
+
+/// K-PKE.Encrypt (FIPS 203 Algorithm 14) of `m` with the randomness `r` for the parameter set `P`.
+///
+/// `ek_pke` must be 384k + 32 bytes and `c` 32(du * k + dv) bytes. The key is not modulus checked,
+/// t is decoded modulo q.
+pub fn pke_encrypt<P: ParameterSet>(ek_pke: &[u8], m: &[u8; 32], r: &[u8; 32], c: &mut [u8]) -> Result<(), Error> {
+    if ek_pke.len() != 384 * P::K + 32 {
+        return Err(Error::InvalidEncapsKey);
+    }
+    if c.len() != 32 * (P::DU as usize * P::K + P::DV as usize) {
+        return Err(Error::InvalidCipherText);
+    }
+
+    let ek = PkeEncryptionKey::new(P::K, ek_pke);
+    k_pke::pke_encrypt_prepared(P::K, P::ETA1, P::ETA2, P::DU, P::DV, &ek, m, r, c);
+    Ok(())
+}
+
+/// K-PKE.Decrypt (FIPS 203 Algorithm 15) of `c` for the parameter set `P`.
+///
+/// `dk_pke` must be 384k bytes and `c` 32(du * k + dv) bytes.
+pub fn pke_decrypt<P: ParameterSet>(dk_pke: &[u8], c: &[u8]) -> Result<Zeroizing<[u8; 32]>, Error> {
+    if dk_pke.len() != 384 * P::K {
+        return Err(Error::InvalidDecapsKey);
+    }
+    if c.len() != 32 * (P::DU as usize * P::K + P::DV as usize) {
+        return Err(Error::InvalidCipherText);
+    }
+
+    let dk = PkeDecryptionKey::new(P::K, dk_pke);
+    Ok(k_pke::pke_decrypt_prepared(P::K, P::DU, P::DV, &dk, c))
+}
+
+/// SampleNTT (FIPS 203 Algorithm 7) of `rho || j || i`, the entry A[i, j] of the matrix A.
+pub fn sample_ntt(rho: &[u8; 32], j: u8, i: u8) -> NttPoly {
+    NttPoly(sample::sample_ntt(rho, j, i))
+}
+
+/// SamplePolyCBD (FIPS 203 Algorithm 8) with eta = 2 for 128 bytes of `b` and eta = 3 for 192 bytes.
+///
+/// # Panics
+///
+/// If `b` is neither 128 nor 192 bytes.
+pub fn sample_poly_cbd(b: &[u8]) -> Poly {
+    assert!(b.len() == 128 || b.len() == 192, "b must be 64 * eta bytes for eta 2 or 3");
+    Poly(sample::sample_poly_cbd(b))
+}
+
+/// NTT (FIPS 203 Algorithm 9).
+pub fn ntt(f: &Poly) -> NttPoly {
+    NttPoly(ntt::ntt(&f.0))
+}
+
+/// NTT^-1 (FIPS 203 Algorithm 10).
+pub fn ntt_inv(f: &NttPoly) -> Poly {
+    Poly(ntt::ntt_inv(&f.0))
+}
+
+/// ByteEncode_d (FIPS 203 Algorithm 5) of integers modulo m = 2^d for d < 12 and m = q for d = 12,
+/// into `output` of 32d bytes. Each integer is reduced modulo m first.
+///
+/// # Panics
+///
+/// If `d` is not between 1 and 12 or `output` is not 32d bytes.
+pub fn byte_encode(f: &[u16; 256], d: u8, output: &mut [u8]) {
+    check_d(d);
+    assert_eq!(output.len(), 32 * d as usize, "output must be 32 * d bytes");
+
+    let f = Zeroizing::new(f.map(|x| if d == 12 { reduce_u16(x) } else { (x & ((1 << d) - 1)) as i16 }));
+    converter::byte_encode(&f, d, output);
+}
+
+/// ByteDecode_d (FIPS 203 Algorithm 6) of 32d bytes, giving integers modulo m = 2^d for d < 12 and
+/// m = q for d = 12.
+///
+/// # Panics
+///
+/// If `d` is not between 1 and 12 or `bytes` is not 32d bytes.
+pub fn byte_decode(bytes: &[u8], d: u8) -> [u16; 256] {
+    check_d(d);
+    assert_eq!(bytes.len(), 32 * d as usize, "bytes must be 32 * d bytes");
+
+    converter::byte_decode(bytes, d).map(|x| x as u16)
+}
+
+/// Compress_d (FIPS 203 §4.2.1) of each coefficient, giving integers modulo 2^d.
+///
+/// # Panics
+///
+/// If `d` is not between 1 and 12.
+pub fn compress(d: u8, f: &Poly) -> [u16; 256] {
+    check_d(d);
+    converter::compress(d, &f.0).map(|y| y as u16)
+}
+
+/// Decompress_d (FIPS 203 §4.2.1) of integers modulo 2^d, each reduced modulo 2^d first.
+///
+/// # Panics
+///
+/// If `d` is not between 1 and 12.
+pub fn decompress(d: u8, y: &[u16; 256]) -> Poly {
+    check_d(d);
+    let y = Zeroizing::new(y.map(|y| (y & ((1 << d) - 1)) as i16));
+    Poly(converter::decompress(d, &y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MlKem512, MlKem768, MlKem1024, Q, hash_utils::{g, h}, params::ByteArray, typed::{EncapsKey, MlKem}, types::DecapsKeySeed};
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{RngCore, SeedableRng};
+
+    fn random_poly(rng: &mut ChaCha20Rng) -> Poly {
+        Poly::from_coefficients(&core::array::from_fn(|_| rng.next_u32() as u16))
+    }
+
+    // K-PKE is the inner scheme of ML-KEM, so its keys and ciphertexts are parts of the ML-KEM ones
+    fn k_pke_matches_ml_kem<P: ParameterSet>() {
+        let mut rng = ChaCha20Rng::seed_from_u64(25);
+        let (mut d, mut z, mut m) = ([0u8; 32], [0u8; 32], [0u8; 32]);
+        rng.fill_bytes(&mut d);
+        rng.fill_bytes(&mut z);
+        rng.fill_bytes(&mut m);
+
+        let kem = MlKem::<P>::new();
+        let (ek, dk) = kem.keygen_from_seed(&DecapsKeySeed::from_d_z(&d, &z));
+        let mut ek_pke = P::EncapsKeyBytes::zeroed();
+        let mut dk_pke = [0u8; 384 * 4];
+        let dk_pke = &mut dk_pke[..384 * P::K];
+        pke_key_gen::<P>(&d, ek_pke.as_mut(), dk_pke).unwrap();
+        assert_eq!(ek_pke.as_ref(), ek.as_bytes().as_ref());
+        assert_eq!(&*dk_pke, &dk.as_bytes().as_ref()[..384 * P::K]);
+
+        let (_, c) = kem.encaps_deterministic(&EncapsKey::from_bytes(ek_pke).unwrap(), &m);
+        let (_, r) = g(&m, &h(ek_pke.as_ref()));
+        let mut c_pke = P::CipherTextBytes::zeroed();
+        pke_encrypt::<P>(ek_pke.as_ref(), &m, &r, c_pke.as_mut()).unwrap();
+        assert_eq!(c_pke.as_ref(), c.as_bytes().as_ref());
+        assert_eq!(*pke_decrypt::<P>(dk_pke, c_pke.as_ref()).unwrap(), m);
+
+        assert_eq!(pke_key_gen::<P>(&d, &mut ek_pke.as_mut()[1..], dk_pke), Err(Error::InvalidEncapsKey));
+        assert_eq!(pke_encrypt::<P>(ek_pke.as_ref(), &m, &r, &mut c_pke.as_mut()[1..]), Err(Error::InvalidCipherText));
+        assert_eq!(pke_decrypt::<P>(&dk_pke[1..], c_pke.as_ref()).err(), Some(Error::InvalidDecapsKey));
+    }
+
+    #[test]
+    fn test_k_pke_matches_ml_kem() {
+        k_pke_matches_ml_kem::<MlKem512>();
+        k_pke_matches_ml_kem::<MlKem768>();
+        k_pke_matches_ml_kem::<MlKem1024>();
+    }
+
+    // The NTT turns multiplication in R_q = Z_q[X] / (X^256 + 1) into MultiplyNTTs
+    #[test]
+    fn test_ntt_product_matches_schoolbook() {
+        let mut rng = ChaCha20Rng::seed_from_u64(25);
+        let (f, g) = (random_poly(&mut rng), random_poly(&mut rng));
+        let (f_coeffs, g_coeffs) = (f.coefficients(), g.coefficients());
+
+        let mut expected = [0i64; 256];
+        for i in 0..256 {
+            for j in 0..256 {
+                let product = f_coeffs[i] as i64 * g_coeffs[j] as i64;
+                if i + j < 256 {
+                    expected[i + j] += product;
+                } else {
+                    expected[i + j - 256] -= product;
+                }
+            }
+        }
+        let expected = expected.map(|x| x.rem_euclid(Q as i64) as u16);
+
+        assert_eq!(ntt_inv(&(&ntt(&f) * &ntt(&g))).coefficients(), expected);
+        assert_eq!(ntt_inv(&ntt(&f)).coefficients(), f_coeffs);
+        assert_eq!((&(&f + &g) - &g).coefficients(), f_coeffs);
+    }
+
+    #[test]
+    fn test_compress_and_encode_roundtrip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(25);
+        let f = random_poly(&mut rng);
+        for d in 1..=12 {
+            let y = compress(d, &f);
+            // ByteEncode_12 works modulo q rather than 2^12, ML-KEM only encodes compressed values with d < 12
+            if d < 12 {
+                let mut bytes = [0u8; 32 * 11];
+                byte_encode(&y, d, &mut bytes[..32 * d as usize]);
+                assert_eq!(byte_decode(&bytes[..32 * d as usize], d), y);
+            }
+
+            // Decompress_d(Compress_d(x)) is within round(q / 2^(d + 1)) of x modulo q
+            let bound = (Q as i32 + (1 << d)) >> (d + 1);
+            for (x, x_prime) in f.coefficients().into_iter().zip(decompress(d, &y).coefficients()) {
+                let diff = (x as i32 - x_prime as i32).rem_euclid(Q as i32);
+                assert!(diff.min(Q as i32 - diff) <= bound, "d = {d}");
+            }
+        }
+
+        // Twelve-bit encodings are reduced modulo q on both sides
+        let mut bytes = [0u8; 384];
+        byte_encode(&[Q as u16 + 1; 256], 12, &mut bytes);
+        assert_eq!(byte_decode(&bytes, 12), [1; 256]);
+        assert_eq!(byte_decode(&[0xff; 384], 12), [4095 - Q as u16; 256]);
+    }
+
+    #[test]
+    fn test_reduction_of_all_u16() {
+        for block in 0..256 {
+            let coefficients: [u16; 256] = core::array::from_fn(|i| (256 * block + i) as u16);
+            let expected = coefficients.map(|c| c % Q as u16);
+            assert_eq!(Poly::from_coefficients(&coefficients).coefficients(), expected);
+            assert_eq!(NttPoly::from_coefficients(&coefficients).coefficients(), expected);
+
+            let mut bytes = [0u8; 384];
+            byte_encode(&coefficients, 12, &mut bytes);
+            assert_eq!(byte_decode(&bytes, 12), expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "d must be between 1 and 12")]
+    fn test_compress_rejects_d_above_12() {
+        compress(13, &Poly::zero());
+    }
+}
@@ -0,0 +1,644 @@
+//! HPKE (RFC 9180) with ML-KEM and X-Wing as the KEM.
+//!
+//! The KEM identifiers and key derivation follow the HPKE post-quantum KEM drafts. The KEM shared
+//! secret enters the key schedule directly, private keys are serialized as the seed their key pair
+//! is expanded from, and `DeriveKeyPair` expands `ikm` into that seed with SHAKE256. The base and
+//! PSK modes are supported, the auth modes need an authenticated KEM.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use aes_gcm::{Aes128Gcm, Aes256Gcm, aead::{Aead as _, KeyInit, Payload}};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::{Hkdf, HkdfExtract};
+use sha2::{Sha256, Sha384, Sha512};
+use sha3::{Shake256, digest::{ExtendableOutput, Update, XofReader}};
+use zeroize::Zeroizing;
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+use rand_core::{TryCryptoRng, TryRngCore};
+
+use crate::{Error, MlKem, MlKemParams, types::{CipherText, DecapsKeySeed, EncapsKey, SharedSecretKey}, xwing::{self, XWing}};
+
+const MODE_BASE: u8 = 0x00;
+const MODE_PSK: u8 = 0x01;
+
+/// A KEM identifier from the HPKE KEM registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Kem {
+    MlKem768 = 0x0041,
+    MlKem1024 = 0x0042,
+    /// The X25519MLKEM768 hybrid, X-Wing over ML-KEM-768 and X25519.
+    MlKem768X25519 = 0x647a,
+}
+
+/// A KDF identifier from the HPKE KDF registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Kdf {
+    HkdfSha256 = 0x0001,
+    HkdfSha384 = 0x0002,
+    HkdfSha512 = 0x0003,
+}
+
+/// An AEAD identifier from the HPKE AEAD registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u16)]
+pub enum Aead {
+    Aes128Gcm = 0x0001,
+    Aes256Gcm = 0x0002,
+    ChaCha20Poly1305 = 0x0003,
+    /// No encryption, the context only exports secrets.
+    ExportOnly = 0xFFFF,
+}
+
+impl Kem {
+    pub fn from_id(id: u16) -> Option<Self> {
+        match id {
+            0x0041 => Some(Kem::MlKem768),
+            0x0042 => Some(Kem::MlKem1024),
+            0x647a => Some(Kem::MlKem768X25519),
+            _ => None
+        }
+    }
+
+    pub fn id(self) -> u16 {
+        self as u16
+    }
+
+    /// `Npk`, the length of a serialized public key.
+    pub fn public_key_len(self) -> usize {
+        match self {
+            Kem::MlKem768 => 1184,
+            Kem::MlKem1024 => 1568,
+            Kem::MlKem768X25519 => xwing::ENCAPS_KEY_LEN,
+        }
+    }
+
+    /// `Nsk`, the length of a serialized private key.
+    pub fn private_key_len(self) -> usize {
+        match self {
+            Kem::MlKem768 | Kem::MlKem1024 => 64,
+            Kem::MlKem768X25519 => xwing::DECAPS_KEY_LEN,
+        }
+    }
+
+    /// `Nenc`, the length of an encapsulated key.
+    pub fn enc_len(self) -> usize {
+        match self {
+            Kem::MlKem768 => 1088,
+            Kem::MlKem1024 => 1568,
+            Kem::MlKem768X25519 => xwing::CIPHERTEXT_LEN,
+        }
+    }
+
+    fn ml_kem(self) -> MlKem {
+        match self {
+            Kem::MlKem1024 => MlKem::new(MlKemParams::MlKem1024),
+            _ => MlKem::new(MlKemParams::MlKem768),
+        }
+    }
+
+    fn public_key(self, sk: &[u8]) -> Vec<u8> {
+        match self {
+            Kem::MlKem768 | Kem::MlKem1024 => {
+                let seed = DecapsKeySeed(sk.try_into().unwrap());
+                self.ml_kem().keygen_from_seed(&seed).0.into_bytes()
+            }
+            Kem::MlKem768X25519 => xwing::DecapsKey::from_bytes(sk.try_into().unwrap()).encaps_key().as_bytes().to_vec(),
+        }
+    }
+
+    // Length of the randomness Encap draws: the message m of ML-KEM or the eseed of X-Wing
+    fn encap_rand_len(self) -> usize {
+        match self {
+            Kem::MlKem768 | Kem::MlKem1024 => 32,
+            Kem::MlKem768X25519 => 64,
+        }
+    }
+
+    fn encap<R: TryCryptoRng + TryRngCore>(self, pk_r: &[u8], rng: &mut R) -> Result<(SharedSecretKey, Vec<u8>), Error> {
+        let mut ikm_e = Zeroizing::new(vec![0u8; self.encap_rand_len()]);
+        rng.try_fill_bytes(&mut ikm_e).map_err(|_| Error::RandomnessFailure)?;
+
+        self.encap_derand(pk_r, &ikm_e)
+    }
+
+    // Encap with its randomness ikm_e of encap_rand_len bytes supplied by the caller
+    fn encap_derand(self, pk_r: &[u8], ikm_e: &[u8]) -> Result<(SharedSecretKey, Vec<u8>), Error> {
+        match self {
+            Kem::MlKem768 | Kem::MlKem1024 => {
+                let (ss, enc) = self.ml_kem().encaps_checked(&EncapsKey::from_slice(pk_r), ikm_e.try_into().unwrap())?;
+                Ok((ss, enc.into_bytes()))
+            }
+            Kem::MlKem768X25519 => {
+                let ek = xwing::EncapsKey::from_slice(pk_r)?;
+                let (ss, enc) = XWing::new().encaps_from_seed(&ek, ikm_e.try_into().unwrap());
+                Ok((ss, enc.as_bytes().to_vec()))
+            }
+        }
+    }
+
+    fn decap(self, enc: &[u8], sk_r: &PrivateKey) -> Result<SharedSecretKey, Error> {
+        if sk_r.kem != self {
+            return Err(Error::InvalidDecapsKey);
+        }
+        match self {
+            Kem::MlKem768 | Kem::MlKem1024 => {
+                let kem = self.ml_kem();
+                let (_, dk) = kem.keygen_from_seed(&DecapsKeySeed(sk_r.seed[..].try_into().unwrap()));
+                kem.decaps(&dk, &CipherText::from_slice(enc))
+            }
+            Kem::MlKem768X25519 => {
+                let dk = xwing::DecapsKey::from_slice(&sk_r.seed)?;
+                Ok(XWing::new().decaps(&dk, &xwing::CipherText::from_slice(enc)?))
+            }
+        }
+    }
+}
+
+impl Kdf {
+    pub fn from_id(id: u16) -> Option<Self> {
+        match id {
+            0x0001 => Some(Kdf::HkdfSha256),
+            0x0002 => Some(Kdf::HkdfSha384),
+            0x0003 => Some(Kdf::HkdfSha512),
+            _ => None
+        }
+    }
+
+    pub fn id(self) -> u16 {
+        self as u16
+    }
+
+    // Nh
+    fn hash_len(self) -> usize {
+        match self {
+            Kdf::HkdfSha256 => 32,
+            Kdf::HkdfSha384 => 48,
+            Kdf::HkdfSha512 => 64,
+        }
+    }
+
+    // HKDF-Extract over the concatenation of ikm
+    fn extract(self, salt: &[u8], ikm: &[&[u8]]) -> Zeroizing<Vec<u8>> {
+        macro_rules! extract {
+            ($hash:ty) => {{
+                let mut hkdf = HkdfExtract::<$hash>::new(Some(salt));
+                for part in ikm {
+                    hkdf.input_ikm(part);
+                }
+                Zeroizing::new(hkdf.finalize().0.to_vec())
+            }};
+        }
+        match self {
+            Kdf::HkdfSha256 => extract!(Sha256),
+            Kdf::HkdfSha384 => extract!(Sha384),
+            Kdf::HkdfSha512 => extract!(Sha512),
+        }
+    }
+
+    // HKDF-Expand with the concatenation of info, okm is at most 255 * Nh bytes
+    fn expand(self, prk: &[u8], info: &[&[u8]], okm: &mut [u8]) -> Result<(), Error> {
+        macro_rules! expand {
+            ($hash:ty) => {
+                Hkdf::<$hash>::from_prk(prk).ok().and_then(|hkdf| hkdf.expand_multi_info(info, okm).ok())
+            };
+        }
+        let expanded = match self {
+            Kdf::HkdfSha256 => expand!(Sha256),
+            Kdf::HkdfSha384 => expand!(Sha384),
+            Kdf::HkdfSha512 => expand!(Sha512),
+        };
+        expanded.ok_or(Error::InvalidExportLength)
+    }
+}
+
+impl Aead {
+    pub fn from_id(id: u16) -> Option<Self> {
+        match id {
+            0x0001 => Some(Aead::Aes128Gcm),
+            0x0002 => Some(Aead::Aes256Gcm),
+            0x0003 => Some(Aead::ChaCha20Poly1305),
+            0xFFFF => Some(Aead::ExportOnly),
+            _ => None
+        }
+    }
+
+    pub fn id(self) -> u16 {
+        self as u16
+    }
+
+    // Nk
+    fn key_len(self) -> usize {
+        match self {
+            Aead::Aes128Gcm => 16,
+            Aead::Aes256Gcm | Aead::ChaCha20Poly1305 => 32,
+            Aead::ExportOnly => 0,
+        }
+    }
+
+    fn cipher(self, key: &[u8]) -> Cipher {
+        match self {
+            Aead::Aes128Gcm => Cipher::Aes128Gcm(Box::new(Aes128Gcm::new_from_slice(key).unwrap())),
+            Aead::Aes256Gcm => Cipher::Aes256Gcm(Box::new(Aes256Gcm::new_from_slice(key).unwrap())),
+            Aead::ChaCha20Poly1305 => Cipher::ChaCha20Poly1305(ChaCha20Poly1305::new_from_slice(key).unwrap()),
+            Aead::ExportOnly => Cipher::ExportOnly,
+        }
+    }
+}
+
+// Nn is 12 for every AEAD that encrypts
+const NONCE_LEN: usize = 12;
+
+enum Cipher {
+    // The AES key schedules are large, boxed to keep the contexts small
+    Aes128Gcm(Box<Aes128Gcm>),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+    ExportOnly,
+}
+
+impl Cipher {
+    fn seal(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, Error> {
+        let payload = Payload { msg: pt, aad };
+        let ct = match self {
+            Cipher::Aes128Gcm(cipher) => cipher.encrypt(nonce.into(), payload),
+            Cipher::Aes256Gcm(cipher) => cipher.encrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305(cipher) => cipher.encrypt(nonce.into(), payload),
+            Cipher::ExportOnly => return Err(Error::ExportOnly),
+        };
+        // Only fails for plaintexts over the AEAD's length limit
+        ct.map_err(|_| Error::MessageLimitReached)
+    }
+
+    fn open(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, Error> {
+        let payload = Payload { msg: ct, aad };
+        let pt = match self {
+            Cipher::Aes128Gcm(cipher) => cipher.decrypt(nonce.into(), payload),
+            Cipher::Aes256Gcm(cipher) => cipher.decrypt(nonce.into(), payload),
+            Cipher::ChaCha20Poly1305(cipher) => cipher.decrypt(nonce.into(), payload),
+            Cipher::ExportOnly => return Err(Error::ExportOnly),
+        };
+        pt.map_err(|_| Error::OpenFailure)
+    }
+}
+
+/// An HPKE private key, serialized as the seed its key pair is expanded from.
+#[derive(Clone)]
+pub struct PrivateKey {
+    kem: Kem,
+    seed: Zeroizing<Vec<u8>>
+}
+
+impl PrivateKey {
+    /// `DeserializePrivateKey`, any `Nsk` bytes are a valid seed.
+    pub fn from_bytes(kem: Kem, bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != kem.private_key_len() {
+            return Err(Error::InvalidDecapsKey);
+        }
+        Ok(PrivateKey { kem, seed: Zeroizing::new(bytes.to_vec()) })
+    }
+
+    pub fn kem(&self) -> Kem { self.kem }
+    pub fn as_bytes(&self) -> &[u8] { &self.seed }
+
+    /// Expands the seed and returns the serialized public key.
+    pub fn public_key(&self) -> Vec<u8> {
+        self.kem.public_key(&self.seed)
+    }
+}
+
+// The key schedule output shared by both directions
+struct Context {
+    kdf: Kdf,
+    suite_id: [u8; 10],
+    cipher: Cipher,
+    base_nonce: [u8; NONCE_LEN],
+    seq: u64,
+    exporter_secret: Zeroizing<Vec<u8>>
+}
+
+impl Context {
+    // base_nonce XOR I2OSP(seq, Nn), the sequence number only advances once the AEAD succeeds
+    fn nonce(&self) -> Result<[u8; NONCE_LEN], Error> {
+        if self.seq == u64::MAX {
+            return Err(Error::MessageLimitReached);
+        }
+        let mut nonce = self.base_nonce;
+        let seq = self.seq.to_be_bytes();
+        for i in 0..seq.len() {
+            nonce[NONCE_LEN - seq.len() + i] ^= seq[i];
+        }
+        Ok(nonce)
+    }
+
+    fn export(&self, exporter_context: &[u8], len: usize) -> Result<Zeroizing<Vec<u8>>, Error> {
+        if len > 255 * self.kdf.hash_len() {
+            return Err(Error::InvalidExportLength);
+        }
+        let mut exported = Zeroizing::new(vec![0u8; len]);
+        labeled_expand(self.kdf, &self.suite_id, &self.exporter_secret, b"sec", exporter_context, &mut exported)?;
+        Ok(exported)
+    }
+}
+
+/// The sender's encryption context returned by `SetupBaseS` and `SetupPSKS`.
+pub struct SenderContext(Context);
+
+/// The recipient's encryption context returned by `SetupBaseR` and `SetupPSKR`.
+pub struct ReceiverContext(Context);
+
+impl SenderContext {
+    /// Encrypts the next message in sequence.
+    pub fn seal(&mut self, aad: &[u8], pt: &[u8]) -> Result<Vec<u8>, Error> {
+        let ct = self.0.cipher.seal(&self.0.nonce()?, aad, pt)?;
+        self.0.seq += 1;
+        Ok(ct)
+    }
+
+    /// Derives `len` bytes bound to `exporter_context` from the exporter secret.
+    pub fn export(&self, exporter_context: &[u8], len: usize) -> Result<Zeroizing<Vec<u8>>, Error> {
+        self.0.export(exporter_context, len)
+    }
+}
+
+impl ReceiverContext {
+    /// Decrypts the next message in sequence.
+    pub fn open(&mut self, aad: &[u8], ct: &[u8]) -> Result<Vec<u8>, Error> {
+        let pt = self.0.cipher.open(&self.0.nonce()?, aad, ct)?;
+        self.0.seq += 1;
+        Ok(pt)
+    }
+
+    /// Derives `len` bytes bound to `exporter_context` from the exporter secret.
+    pub fn export(&self, exporter_context: &[u8], len: usize) -> Result<Zeroizing<Vec<u8>>, Error> {
+        self.0.export(exporter_context, len)
+    }
+}
+
+// LabeledExtract(salt, label, ikm) = Extract(salt, "HPKE-v1" || suite_id || label || ikm)
+fn labeled_extract(kdf: Kdf, suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8]) -> Zeroizing<Vec<u8>> {
+    kdf.extract(salt, &[b"HPKE-v1", suite_id, label, ikm])
+}
+
+// LabeledExpand(prk, label, info, L) = Expand(prk, I2OSP(L, 2) || "HPKE-v1" || suite_id || label || info, L)
+fn labeled_expand(kdf: Kdf, suite_id: &[u8], prk: &[u8], label: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), Error> {
+    let len = u16::try_from(okm.len()).map_err(|_| Error::InvalidExportLength)?.to_be_bytes();
+    kdf.expand(prk, &[&len, b"HPKE-v1", suite_id, label, info], okm)
+}
+
+/// An HPKE ciphersuite.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hpke {
+    kem: Kem,
+    kdf: Kdf,
+    aead: Aead
+}
+
+impl Hpke {
+    pub fn new(kem: Kem, kdf: Kdf, aead: Aead) -> Self {
+        Hpke { kem, kdf, aead }
+    }
+
+    pub fn kem(&self) -> Kem { self.kem }
+    pub fn kdf(&self) -> Kdf { self.kdf }
+    pub fn aead(&self) -> Aead { self.aead }
+
+    // "HPKE" || I2OSP(kem_id, 2) || I2OSP(kdf_id, 2) || I2OSP(aead_id, 2)
+    fn suite_id(&self) -> [u8; 10] {
+        let mut suite_id = *b"HPKE\0\0\0\0\0\0";
+        suite_id[4..6].copy_from_slice(&self.kem.id().to_be_bytes());
+        suite_id[6..8].copy_from_slice(&self.kdf.id().to_be_bytes());
+        suite_id[8..].copy_from_slice(&self.aead.id().to_be_bytes());
+        suite_id
+    }
+
+    /// `DeriveKeyPair`, expands `ikm` with SHAKE256 into the private key seed.
+    pub fn derive_key_pair(&self, ikm: &[u8]) -> (Vec<u8>, PrivateKey) {
+        let mut seed = Zeroizing::new(vec![0u8; self.kem.private_key_len()]);
+        let mut hasher = Shake256::default();
+        hasher.update(ikm);
+        hasher.finalize_xof().read(&mut seed);
+
+        let sk = PrivateKey { kem: self.kem, seed };
+        (sk.public_key(), sk)
+    }
+
+    pub fn generate_key_pair_with_rng<R: TryCryptoRng + TryRngCore>(&self, rng: &mut R) -> Result<(Vec<u8>, PrivateKey), Error> {
+        let mut ikm = Zeroizing::new(vec![0u8; self.kem.private_key_len()]);
+        rng.try_fill_bytes(&mut ikm).map_err(|_| Error::RandomnessFailure)?;
+
+        Ok(self.derive_key_pair(&ikm))
+    }
+
+    pub fn setup_base_s_with_rng<R: TryCryptoRng + TryRngCore>(&self, pk_r: &[u8], info: &[u8], rng: &mut R) -> Result<(Vec<u8>, SenderContext), Error> {
+        let (shared_secret, enc) = self.kem.encap(pk_r, rng)?;
+        let context = self.key_schedule(MODE_BASE, &shared_secret, info, b"", b"")?;
+
+        Ok((enc, SenderContext(context)))
+    }
+
+    pub fn setup_base_r(&self, enc: &[u8], sk_r: &PrivateKey, info: &[u8]) -> Result<ReceiverContext, Error> {
+        let shared_secret = self.kem.decap(enc, sk_r)?;
+
+        Ok(ReceiverContext(self.key_schedule(MODE_BASE, &shared_secret, info, b"", b"")?))
+    }
+
+    pub fn setup_psk_s_with_rng<R: TryCryptoRng + TryRngCore>(&self, pk_r: &[u8], info: &[u8], psk: &[u8], psk_id: &[u8], rng: &mut R) -> Result<(Vec<u8>, SenderContext), Error> {
+        verify_psk_inputs(MODE_PSK, psk, psk_id)?;
+        let (shared_secret, enc) = self.kem.encap(pk_r, rng)?;
+        let context = self.key_schedule(MODE_PSK, &shared_secret, info, psk, psk_id)?;
+
+        Ok((enc, SenderContext(context)))
+    }
+
+    pub fn setup_psk_r(&self, enc: &[u8], sk_r: &PrivateKey, info: &[u8], psk: &[u8], psk_id: &[u8]) -> Result<ReceiverContext, Error> {
+        verify_psk_inputs(MODE_PSK, psk, psk_id)?;
+        let shared_secret = self.kem.decap(enc, sk_r)?;
+
+        Ok(ReceiverContext(self.key_schedule(MODE_PSK, &shared_secret, info, psk, psk_id)?))
+    }
+
+    fn key_schedule(&self, mode: u8, shared_secret: &SharedSecretKey, info: &[u8], psk: &[u8], psk_id: &[u8]) -> Result<Context, Error> {
+        verify_psk_inputs(mode, psk, psk_id)?;
+        let suite_id = self.suite_id();
+        let kdf = self.kdf;
+
+        let psk_id_hash = labeled_extract(kdf, &suite_id, b"", b"psk_id_hash", psk_id);
+        let info_hash = labeled_extract(kdf, &suite_id, b"", b"info_hash", info);
+        let mut key_schedule_context = Vec::with_capacity(1 + psk_id_hash.len() + info_hash.len());
+        key_schedule_context.push(mode);
+        key_schedule_context.extend_from_slice(&psk_id_hash);
+        key_schedule_context.extend_from_slice(&info_hash);
+
+        let secret = labeled_extract(kdf, &suite_id, &shared_secret.0, b"secret", psk);
+
+        let mut base_nonce = [0u8; NONCE_LEN];
+        let cipher = if self.aead == Aead::ExportOnly {
+            Cipher::ExportOnly
+        } else {
+            let mut key = Zeroizing::new(vec![0u8; self.aead.key_len()]);
+            labeled_expand(kdf, &suite_id, &secret, b"key", &key_schedule_context, &mut key)?;
+            labeled_expand(kdf, &suite_id, &secret, b"base_nonce", &key_schedule_context, &mut base_nonce)?;
+            self.aead.cipher(&key)
+        };
+
+        let mut exporter_secret = Zeroizing::new(vec![0u8; kdf.hash_len()]);
+        labeled_expand(kdf, &suite_id, &secret, b"exp", &key_schedule_context, &mut exporter_secret)?;
+
+        Ok(Context { kdf, suite_id, cipher, base_nonce, seq: 0, exporter_secret })
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn generate_key_pair(&self) -> Result<(Vec<u8>, PrivateKey), Error> {
+        self.generate_key_pair_with_rng(&mut OsRng)
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn setup_base_s(&self, pk_r: &[u8], info: &[u8]) -> Result<(Vec<u8>, SenderContext), Error> {
+        self.setup_base_s_with_rng(pk_r, info, &mut OsRng)
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn setup_psk_s(&self, pk_r: &[u8], info: &[u8], psk: &[u8], psk_id: &[u8]) -> Result<(Vec<u8>, SenderContext), Error> {
+        self.setup_psk_s_with_rng(pk_r, info, psk, psk_id, &mut OsRng)
+    }
+}
+
+// The PSK and its ID are both empty in base mode and both set in PSK mode
+fn verify_psk_inputs(mode: u8, psk: &[u8], psk_id: &[u8]) -> Result<(), Error> {
+    let got_psk = !psk.is_empty();
+    let got_psk_id = !psk_id.is_empty();
+    if got_psk != got_psk_id || got_psk != (mode == MODE_PSK) {
+        return Err(Error::InvalidPsk);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+    use serde_json::Value;
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn suite(vector: &Value) -> Hpke {
+        let id = |name: &str| vector[name].as_u64().unwrap() as u16;
+        Hpke::new(Kem::from_id(id("kem_id")).unwrap(), Kdf::from_id(id("kdf_id")).unwrap(), Aead::from_id(id("aead_id")).unwrap())
+    }
+
+    // Vectors in the RFC 9180 Appendix A layout
+    #[test]
+    fn test_vectors() {
+        let vectors: Value = serde_json::from_str(include_str!("../tests/data/hpke_test_vectors.json")).unwrap();
+
+        for vector in vectors.as_array().unwrap() {
+            let field = |name: &str| decode_hex(vector[name].as_str().unwrap());
+            let hpke = suite(vector);
+            let (info, psk, psk_id) = (field("info"), field("psk"), field("psk_id"));
+
+            let (pk_r, sk_r) = hpke.derive_key_pair(&field("ikmR"));
+            assert_eq!(pk_r, field("pkRm"));
+            assert_eq!(sk_r.as_bytes(), field("skRm"));
+
+            // SetupBaseS and SetupPSKS with the encapsulation randomness ikmE of the vector
+            let mode = vector["mode"].as_u64().unwrap() as u8;
+            let (shared_secret, enc) = hpke.kem.encap_derand(&pk_r, &field("ikmE")).unwrap();
+            let mut sender = SenderContext(hpke.key_schedule(mode, &shared_secret, &info, &psk, &psk_id).unwrap());
+            let mut receiver = match mode {
+                MODE_BASE => hpke.setup_base_r(&enc, &sk_r, &info).unwrap(),
+                _ => hpke.setup_psk_r(&enc, &sk_r, &info, &psk, &psk_id).unwrap(),
+            };
+            assert_eq!(enc, field("enc"));
+            assert_eq!(sender.0.exporter_secret[..], field("exporter_secret")[..]);
+            assert_eq!(receiver.0.exporter_secret[..], field("exporter_secret")[..]);
+            if hpke.aead != Aead::ExportOnly {
+                assert_eq!(sender.0.base_nonce[..], field("base_nonce")[..]);
+            }
+
+            for encryption in vector["encryptions"].as_array().unwrap() {
+                let field = |name: &str| decode_hex(encryption[name].as_str().unwrap());
+                assert_eq!(sender.0.nonce().unwrap()[..], field("nonce")[..]);
+                assert_eq!(sender.seal(&field("aad"), &field("pt")).unwrap(), field("ct"));
+                assert_eq!(receiver.open(&field("aad"), &field("ct")).unwrap(), field("pt"));
+            }
+
+            for export in vector["exports"].as_array().unwrap() {
+                let exporter_context = decode_hex(export["exporter_context"].as_str().unwrap());
+                let len = export["L"].as_u64().unwrap() as usize;
+                let expected = decode_hex(export["exported_value"].as_str().unwrap());
+                assert_eq!(sender.export(&exporter_context, len).unwrap()[..], expected[..]);
+                assert_eq!(receiver.export(&exporter_context, len).unwrap()[..], expected[..]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(9180);
+        for kem in [Kem::MlKem768, Kem::MlKem1024, Kem::MlKem768X25519] {
+            let hpke = Hpke::new(kem, Kdf::HkdfSha256, Aead::ChaCha20Poly1305);
+            let (pk_r, sk_r) = hpke.generate_key_pair_with_rng(&mut rng).unwrap();
+            assert_eq!(pk_r.len(), kem.public_key_len());
+
+            let (enc, mut sender) = hpke.setup_base_s_with_rng(&pk_r, b"info", &mut rng).unwrap();
+            assert_eq!(enc.len(), kem.enc_len());
+            let mut receiver = hpke.setup_base_r(&enc, &sk_r, b"info").unwrap();
+
+            let ct = sender.seal(b"aad", b"message").unwrap();
+            assert_eq!(receiver.open(b"other aad", &ct), Err(Error::OpenFailure));
+            assert_eq!(receiver.open(b"aad", &ct).unwrap(), b"message");
+            // Replaying a ciphertext fails since the sequence number has moved on
+            assert_eq!(receiver.open(b"aad", &ct), Err(Error::OpenFailure));
+
+            // A mismatched info or a tampered enc gives an unrelated key, found when opening
+            let (enc, mut sender) = hpke.setup_base_s_with_rng(&pk_r, b"info", &mut rng).unwrap();
+            let ct = sender.seal(b"", b"message").unwrap();
+            let mut tampered = enc.clone();
+            tampered[0] ^= 1;
+            assert_eq!(hpke.setup_base_r(&tampered, &sk_r, b"info").unwrap().open(b"", &ct), Err(Error::OpenFailure));
+            assert_eq!(hpke.setup_base_r(&enc, &sk_r, b"other info").unwrap().open(b"", &ct), Err(Error::OpenFailure));
+            assert_eq!(hpke.setup_base_r(&enc, &sk_r, b"info").unwrap().open(b"", &ct).unwrap(), b"message");
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_inputs() {
+        let mut rng = ChaCha20Rng::seed_from_u64(9181);
+        let hpke = Hpke::new(Kem::MlKem768, Kdf::HkdfSha256, Aead::Aes128Gcm);
+        let (pk_r, sk_r) = hpke.generate_key_pair_with_rng(&mut rng).unwrap();
+
+        assert_eq!(hpke.setup_psk_s_with_rng(&pk_r, b"", b"", b"id", &mut rng).err(), Some(Error::InvalidPsk));
+        assert_eq!(hpke.setup_psk_s_with_rng(&pk_r, b"", &[7u8; 32], b"", &mut rng).err(), Some(Error::InvalidPsk));
+        assert_eq!(hpke.setup_base_s_with_rng(&pk_r[1..], b"", &mut rng).err(), Some(Error::InvalidEncapsKey));
+
+        let (enc, mut sender) = hpke.setup_psk_s_with_rng(&pk_r, b"", &[7u8; 32], b"id", &mut rng).unwrap();
+        assert_eq!(hpke.setup_base_r(&enc[1..], &sk_r, b"").err(), Some(Error::InvalidCipherText));
+        assert!(hpke.setup_psk_r(&enc, &sk_r, b"", &[8u8; 32], b"id").unwrap().open(b"", &sender.seal(b"", b"").unwrap()).is_err());
+
+        // A private key for another KEM
+        let other = Hpke::new(Kem::MlKem1024, Kdf::HkdfSha256, Aead::Aes128Gcm);
+        let (_, sk_other) = other.generate_key_pair_with_rng(&mut rng).unwrap();
+        assert_eq!(hpke.setup_base_r(&enc, &sk_other, b"").err(), Some(Error::InvalidDecapsKey));
+        assert_eq!(PrivateKey::from_bytes(Kem::MlKem768X25519, sk_r.as_bytes()).err(), Some(Error::InvalidDecapsKey));
+
+        assert_eq!(sender.export(b"", 255 * 32).unwrap().len(), 255 * 32);
+        assert_eq!(sender.export(b"", 255 * 32 + 1).err(), Some(Error::InvalidExportLength));
+
+        sender.0.seq = u64::MAX;
+        assert_eq!(sender.seal(b"", b"").err(), Some(Error::MessageLimitReached));
+
+        let export_only = Hpke::new(Kem::MlKem768, Kdf::HkdfSha256, Aead::ExportOnly);
+        let (enc, mut sender) = export_only.setup_base_s_with_rng(&pk_r, b"", &mut rng).unwrap();
+        let mut receiver = export_only.setup_base_r(&enc, &sk_r, b"").unwrap();
+        assert_eq!(sender.seal(b"", b"").err(), Some(Error::ExportOnly));
+        assert_eq!(receiver.open(b"", b"").err(), Some(Error::ExportOnly));
+        assert_eq!(sender.export(b"ctx", 32).unwrap(), receiver.export(b"ctx", 32).unwrap());
+    }
+}
@@ -1,102 +1,162 @@
-use crate::{converter::{byte_decode, byte_encode, byte_encode_mult, compress, decompress}, hash_utils::{g_33, prf},
-ntt::{ntt, ntt_inv, poly_add, poly_mat_mult, poly_mat_transpose_mult, poly_sub, poly_vec_add, poly_vec_mult}, sample::{sample_ntt, sample_poly_cbd}};
+use crate::{MAX_K, converter::{byte_decode, byte_encode, byte_encode_mult, compress, decompress}, hash_utils::{PRF_MAX_LEN, g_33, prf, prf_x4},
+ntt::{PolyMatrix, PolyVec, ntt, ntt_inv, poly_add, poly_mat_mult, poly_mat_transpose_mult, poly_sub, poly_vec_add, poly_vec_mult}, sample::{sample_ntt, sample_ntt_x4, sample_poly_cbd}};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-pub(crate) fn pke_key_gen(k: usize, eta1: u8, d: &[u8; 32]) -> (Vec<u8>, Vec<u8>) {
-    let (rho, sigma) = g_33(d, k.try_into().unwrap());
-    let mut n = 0;
-    let mut a_mtx = vec![vec![[0u16; 256]; k]; k];
-    for i in 0..k {
-        for j in 0..k {
-            a_mtx[i][j] = sample_ntt(&rho, j as u8, i as u8);
+// Samples the k x k matrix A in the NTT domain from the seed rho, four entries at a time. A single
+// leftover entry is sampled on its own, otherwise the unused lanes repeat the last entry.
+fn sample_matrix(k: usize, rho: &[u8; 32]) -> PolyMatrix {
+    let mut a_mtx = [[[0i16; 256]; MAX_K]; MAX_K];
+    for start in (0..k * k).step_by(4) {
+        let count = (k * k - start).min(4);
+        if count == 1 {
+            let (i, j) = (start / k, start % k);
+            a_mtx[i][j] = sample_ntt(rho, j as u8, i as u8);
+            continue;
+        }
+        let entries: [(usize, usize); 4] = core::array::from_fn(|lane| {
+            let n = start + lane.min(count - 1);
+            (n / k, n % k)
+        });
+        let polys = sample_ntt_x4(rho, entries.map(|(i, j)| (j as u8, i as u8)));
+        for (&(i, j), poly) in entries[..count].iter().zip(polys) {
+            a_mtx[i][j] = poly;
         }
     }
-    let mut s = vec![[0u16; 256]; k];
-    for i in 0..k {
-        s[i] = sample_poly_cbd(&prf(eta1, &sigma, n));
-        n += 1;
-    }
-    let mut e = vec![[0u16; 256]; k];
-    for i in 0..k {
-        e[i] = sample_poly_cbd(&prf(eta1, &sigma, n));
-        n += 1;
+    a_mtx
+}
+
+// Samples polys[n] from CBD_eta[n](PRF_eta[n](seed, n)), four PRF streams at a time like sample_matrix
+fn sample_noise(seed: &[u8; 32], eta: &[u8], polys: &mut [[i16; 256]]) {
+    debug_assert_eq!(eta.len(), polys.len());
+
+    let mut prf_output = Zeroizing::new([[0u8; PRF_MAX_LEN]; 4]);
+    for start in (0..polys.len()).step_by(4) {
+        let count = (polys.len() - start).min(4);
+        if count == 1 {
+            polys[start] = sample_poly_cbd(prf(eta[start], seed, start as u8, &mut prf_output[0]));
+            continue;
+        }
+        let n: [usize; 4] = core::array::from_fn(|lane| start + lane.min(count - 1));
+        let outputs = prf_x4(n.map(|n| eta[n]), seed, n.map(|n| n as u8), &mut prf_output);
+        for (poly, output) in polys[start..start + count].iter_mut().zip(outputs) {
+            *poly = sample_poly_cbd(output);
+        }
     }
-    let s_ntt: Vec<[u16; 256]> = s.iter().map(|poly| ntt(poly)).collect();
-    let e_ntt: Vec<[u16; 256]> = e.iter().map(|poly| ntt(poly)).collect();
-    let t = poly_vec_add(&poly_mat_mult(&a_mtx, &s_ntt), &e_ntt); 
-    let mut ek_pke = byte_encode_mult(k, &t, 12);
-    ek_pke.extend(rho);
-    let dk_pke = byte_encode_mult(k, &s_ntt, 12);
-    (ek_pke, dk_pke)
 }
 
-pub(crate) fn pke_encrypt(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, ek: &[u8], m: &[u8; 32], r: &[u8; 32]) -> Vec<u8> {
-    let mut n = 0;
-    let mut t = Vec::with_capacity(k);
-    for i in 0..k {
-        t.push(byte_decode(&ek[384 * i ..384 * (i + 1)], 12));
+// Applies the NTT to each polynomial of the vector
+fn ntt_vec(v: &[[i16; 256]]) -> PolyVec {
+    let mut v_ntt = [[0i16; 256]; MAX_K];
+    for (v_ntt, v) in v_ntt.iter_mut().zip(v) {
+        *v_ntt = ntt(v);
     }
-    let rho: [u8; 32] = ek[384 * k .. 384 * k + 32].try_into().unwrap();
-    let mut a_mtx = vec![vec![[0u16; 256]; k]; k];
-    for i in 0..k {
-        for j in 0..k {
-            a_mtx[i][j] = sample_ntt(&rho, j as u8, i as u8);
+    v_ntt
+}
+
+// ek_pke must be 384k + 32 bytes and dk_pke must be 384k bytes
+pub(crate) fn pke_key_gen(k: usize, eta1: u8, d: &[u8; 32], ek_pke: &mut [u8], dk_pke: &mut [u8]) {
+    debug_assert!(ek_pke.len() == 384 * k + 32, "Encryption key must be 384k + 32 bytes");
+    debug_assert!(dk_pke.len() == 384 * k, "Decryption key must be 384k bytes");
+
+    let (rho, sigma) = g_33(d, k.try_into().unwrap());
+    let sigma = Zeroizing::new(sigma);
+    let a_mtx = sample_matrix(k, &rho);
+    // s and e use the nonces 0 to 2k - 1
+    let mut noise = Zeroizing::new([[0i16; 256]; 2 * MAX_K]);
+    sample_noise(&sigma, &[eta1; 2 * MAX_K][..2 * k], &mut noise[..2 * k]);
+    let (s, e) = noise[..2 * k].split_at(k);
+    let s_ntt = Zeroizing::new(ntt_vec(s));
+    let e_ntt = Zeroizing::new(ntt_vec(e));
+    let mut t = poly_mat_mult(&a_mtx, &s_ntt[..k]);
+    poly_vec_add(&mut t[..k], &e_ntt[..k]);
+    byte_encode_mult(k, &t[..k], 12, &mut ek_pke[..384 * k]);
+    ek_pke[384 * k..].copy_from_slice(&rho);
+    byte_encode_mult(k, &s_ntt[..k], 12, dk_pke);
+}
+
+// The parsed encryption key: t and the matrix A, both in the NTT domain
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct PkeEncryptionKey {
+    t: PolyVec,
+    a_mtx: PolyMatrix,
+}
+
+impl PkeEncryptionKey {
+    pub(crate) fn new(k: usize, ek: &[u8]) -> Self {
+        let mut t = [[0i16; 256]; MAX_K];
+        for i in 0..k {
+            t[i] = byte_decode(&ek[384 * i ..384 * (i + 1)], 12);
         }
+        let rho: [u8; 32] = ek[384 * k .. 384 * k + 32].try_into().unwrap();
+        PkeEncryptionKey { t, a_mtx: sample_matrix(k, &rho) }
     }
-    let mut y = vec![[0u16; 256]; k];
-    for i in 0..k {
-        y[i] = sample_poly_cbd(&prf(eta1, &r, n));
-        n += 1;
-    }
-    let mut e1 = vec![[0u16; 256]; k];
-    for i in 0..k {
-        e1[i] = sample_poly_cbd(&prf(eta2, &r, n));
-        n += 1;
+}
+
+// The parsed decryption key: s in the NTT domain
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct PkeDecryptionKey {
+    s: PolyVec,
+}
+
+impl PkeDecryptionKey {
+    pub(crate) fn new(k: usize, dk: &[u8]) -> Self {
+        let mut s = [[0i16; 256]; MAX_K];
+        for i in 0..k {
+            s[i] = byte_decode(&dk[32 * 12 * i .. 32 * 12 * (i + 1)], 12);
+        }
+        PkeDecryptionKey { s }
     }
-    let e2 = sample_poly_cbd(&prf(eta2, r, n));
-    let y_ntt: Vec<[u16; 256]> = y.iter().map(|poly| ntt(poly)).collect();
+}
+
+// c must be 32(du * k + dv) bytes
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pke_encrypt_prepared(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, ek: &PkeEncryptionKey, m: &[u8; 32], r: &[u8; 32], c: &mut [u8]) {
+    debug_assert!(c.len() == 32 * ((du as usize) * k + dv as usize), "Ciphertext must be of length 32(du * k + dv)");
+
+    // y, e1 and e2 use the nonces 0 to 2k
+    let mut eta = [eta2; 2 * MAX_K + 1];
+    eta[..k].fill(eta1);
+    let mut noise = Zeroizing::new([[0i16; 256]; 2 * MAX_K + 1]);
+    sample_noise(r, &eta[..2 * k + 1], &mut noise[..2 * k + 1]);
+    let (y, e1) = noise[..2 * k].split_at(k);
+    let e2 = &noise[2 * k];
+    let y_ntt = Zeroizing::new(ntt_vec(y));
     // Compute u = NTT_inv(A^T \cdot y_ntt) + e_1
-    let mut u = poly_mat_transpose_mult(&a_mtx, &y_ntt);
-    for (u_i, e1_i) in u.iter_mut().zip(e1.iter()) {
+    let mut u = Zeroizing::new(poly_mat_transpose_mult(&ek.a_mtx, &y_ntt[..k]));
+    for (u_i, e1_i) in u[..k].iter_mut().zip(e1.iter()) {
         *u_i = ntt_inv(u_i);
         poly_add(u_i, e1_i);
     }
-    let mu = decompress(1, &byte_decode(m, 1));
+    let mu = Zeroizing::new(decompress(1, &Zeroizing::new(byte_decode(m, 1))));
     // Compute v = NTT_inv(t \cdot y_ntt) + e_2 + mu
-    let mut v = ntt_inv(&poly_vec_mult(&t, &y_ntt));
-    poly_add(&mut v, &e2);
+    let mut v = Zeroizing::new(ntt_inv(&Zeroizing::new(poly_vec_mult(&ek.t[..k], &y_ntt[..k]))));
+    poly_add(&mut v, e2);
     poly_add(&mut v, &mu);
     // Compute c1 = ByteEncode(Compress(u))
-    let mut comp_u = Vec::with_capacity(k);
+    let (c1, c2) = c.split_at_mut(32 * (du as usize) * k);
     for i in 0..k {
-        comp_u.push(compress(du, &u[i]));
+        byte_encode(&compress(du, &u[i]), du, &mut c1[32 * (du as usize) * i .. 32 * (du as usize) * (i + 1)]);
     }
-    let mut c = byte_encode_mult(k, &comp_u, du);
     // Compute c2 = ByteEncode(Compress(v))
-    c.append(&mut byte_encode(&compress(dv, &v), dv));
-    
-    c
+    byte_encode(&compress(dv, &v), dv, c2);
 }
 
-pub(crate) fn pke_decrypt(k: usize, du: u8, dv: u8, dk: &[u8], c: &[u8]) -> Vec<u8> {
+pub(crate) fn pke_decrypt_prepared(k: usize, du: u8, dv: u8, dk: &PkeDecryptionKey, c: &[u8]) -> Zeroizing<[u8; 32]> {
     debug_assert!(c.len() == 32 * ((du as usize) * k + dv as usize), "Input byte array must be of length 32 * d");
 
-    let mut u = Vec::with_capacity(k);
+    let mut u = [[0i16; 256]; MAX_K];
     for i in 0..k {
-        u.push(decompress(du, &byte_decode(&c[32 * (du as usize) * i .. 32 * (du as usize) * (i + 1)], du)));
+        u[i] = decompress(du, &byte_decode(&c[32 * (du as usize) * i .. 32 * (du as usize) * (i + 1)], du));
     }
 
     let v = decompress(dv, &byte_decode(&c[32 * (du as usize) * k .. 32 * ((du as usize) * k + (dv as usize))], dv));
 
-    let mut s = Vec::with_capacity(k);
-    for i in 0..k {
-        s.push(byte_decode(&dk[32 * 12 * i .. 32 * 12 * (i + 1)], 12));
-    }
-
     // Compute w = v' - NTT^-1(s \cdot NTT(u'))
-    let u_ntt: Vec<[u16; 256]> = u.iter().map(|poly| ntt(poly)).collect();
-    let w = poly_sub(&v, &ntt_inv(&poly_vec_mult(&s, &u_ntt)));
-
-    let m = byte_encode(&compress(1, &w), 1);
+    let u_ntt = ntt_vec(&u[..k]);
+    let s_u = Zeroizing::new(ntt_inv(&Zeroizing::new(poly_vec_mult(&dk.s[..k], &u_ntt[..k]))));
+    let w = Zeroizing::new(poly_sub(&v, &s_u));
 
+    let mut m = Zeroizing::new([0u8; 32]);
+    byte_encode(&Zeroizing::new(compress(1, &w)), 1, m.as_mut());
     m
 }
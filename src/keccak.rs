@@ -0,0 +1,193 @@
+// Four-way Keccak-f[1600] and the SHAKE128 and SHAKE256 instances of sample_ntt and the PRF
+//
+// Four independent Keccak states are permuted together, with word i of every state stored next to
+// each other so that the AVX2 backend holds it in one register. Every input here fits in a single
+// block, so absorbing is a single XOR of the padded inputs and squeezing is whole rate-sized blocks.
+// The output of each lane is byte-identical to the sha3 crate on the same input.
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use crate::avx2;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+// Word i of the four states
+pub(crate) type KeccakState4 = [[u64; 4]; 25];
+
+pub(crate) const SHAKE128_RATE: usize = 168;
+pub(crate) const SHAKE256_RATE: usize = 136;
+
+pub(crate) const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+// rho and pi together move the word at PI_CHAIN[i - 1] (1 for i = 0) to PI_CHAIN[i], rotating it left
+// by RHO_CHAIN[i]. Word 0 stays in place without rotation.
+pub(crate) const PI_CHAIN: [usize; 24] = [10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1];
+pub(crate) const RHO_CHAIN: [u32; 24] = [1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44];
+
+// The 24 rounds on a state of 25 words of any lane type, given its xor, and-not (!a & b), rotate left
+// and broadcast operations. The portable and AVX2 permutations only differ in those operations.
+macro_rules! keccak_rounds {
+    ($a:ident, $xor:ident, $andnot:ident, $rotl:ident, $splat:ident) => {
+        for rc in $crate::keccak::ROUND_CONSTANTS {
+            // theta
+            let mut c = [$a[0]; 5];
+            for (x, c) in c.iter_mut().enumerate() {
+                *c = $xor($xor($xor($a[x], $a[x + 5]), $xor($a[x + 10], $a[x + 15])), $a[x + 20]);
+            }
+            for x in 0..5 {
+                let d = $xor(c[(x + 4) % 5], $rotl(c[(x + 1) % 5], 1));
+                for y in 0..5 {
+                    $a[x + 5 * y] = $xor($a[x + 5 * y], d);
+                }
+            }
+            // rho and pi
+            let mut last = $a[1];
+            for (&i, &r) in $crate::keccak::PI_CHAIN.iter().zip(&$crate::keccak::RHO_CHAIN) {
+                let next = $a[i];
+                $a[i] = $rotl(last, r);
+                last = next;
+            }
+            // chi
+            for y in 0..5 {
+                let row = [$a[5 * y], $a[5 * y + 1], $a[5 * y + 2], $a[5 * y + 3], $a[5 * y + 4]];
+                for x in 0..5 {
+                    $a[x + 5 * y] = $xor(row[x], $andnot(row[(x + 1) % 5], row[(x + 2) % 5]));
+                }
+            }
+            // iota
+            $a[0] = $xor($a[0], $splat(rc));
+        }
+    };
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) use keccak_rounds;
+
+pub(crate) fn keccak_f1600_x4(state: &mut KeccakState4) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if avx2::enabled() {
+        // SAFETY: the CPU supports AVX2, the only target feature the function enables
+        return unsafe { avx2::keccak_f1600_x4(state) };
+    }
+    keccak_f1600_x4_portable(state)
+}
+
+#[inline(always)]
+fn xor(a: u64, b: u64) -> u64 {
+    a ^ b
+}
+
+#[inline(always)]
+fn andnot(a: u64, b: u64) -> u64 {
+    !a & b
+}
+
+#[inline(always)]
+fn rotl(a: u64, n: u32) -> u64 {
+    a.rotate_left(n)
+}
+
+#[inline(always)]
+fn splat(w: u64) -> u64 {
+    w
+}
+
+// Without SIMD the lanes are permuted one after another, where the scalar rotations are cheapest
+pub(crate) fn keccak_f1600_x4_portable(state: &mut KeccakState4) {
+    for lane in 0..4 {
+        let mut a = state.map(|word| word[lane]);
+        keccak_rounds!(a, xor, andnot, rotl, splat);
+        for (word, a) in state.iter_mut().zip(a) {
+            word[lane] = a;
+        }
+    }
+}
+
+// Four SHAKE instances with the given rate in bytes, each absorbing an input shorter than a block.
+// The PRF absorbs secret seeds, so the state is zeroized on drop.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub(crate) struct ShakeX4<const RATE: usize> {
+    state: KeccakState4,
+}
+
+pub(crate) type Shake128X4 = ShakeX4<SHAKE128_RATE>;
+pub(crate) type Shake256X4 = ShakeX4<SHAKE256_RATE>;
+
+impl<const RATE: usize> ShakeX4<RATE> {
+    pub(crate) fn new(inputs: [&[u8]; 4]) -> Self {
+        let mut state = [[0u64; 4]; 25];
+        for (lane, input) in inputs.into_iter().enumerate() {
+            debug_assert!(input.len() < RATE, "Input must fit in one block");
+            // The SHAKE domain separation bits 1111 followed by the first and last padding bits
+            let mut block = [0u8; RATE];
+            block[..input.len()].copy_from_slice(input);
+            block[input.len()] ^= 0x1f;
+            block[RATE - 1] ^= 0x80;
+            for (word, bytes) in state.iter_mut().zip(block.as_chunks::<8>().0) {
+                word[lane] = u64::from_le_bytes(*bytes);
+            }
+            block.zeroize();
+        }
+        ShakeX4 { state }
+    }
+
+    // Writes the next block of output of each lane
+    pub(crate) fn squeeze_block(&mut self, blocks: &mut [[u8; RATE]; 4]) {
+        keccak_f1600_x4(&mut self.state);
+        for (lane, block) in blocks.iter_mut().enumerate() {
+            for (bytes, word) in block.as_chunks_mut::<8>().0.iter_mut().zip(&self.state) {
+                *bytes = word[lane].to_le_bytes();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{RngCore, SeedableRng};
+    use sha3::{Shake128, Shake256, digest::{ExtendableOutput, Update, XofReader}};
+
+    fn squeeze_matches_sha3<const RATE: usize, D: Default + Update + ExtendableOutput>() {
+        let mut rng = ChaCha20Rng::seed_from_u64(20);
+        // Every input length a single block allows, in four different lanes
+        for len in 0..RATE {
+            let mut inputs = [[0u8; RATE]; 4];
+            for input in &mut inputs {
+                rng.fill_bytes(&mut input[..len]);
+            }
+            let mut xof = ShakeX4::<RATE>::new(inputs.each_ref().map(|input| &input[..len]));
+            let mut readers = inputs.map(|input| {
+                let mut hasher = D::default();
+                hasher.update(&input[..len]);
+                hasher.finalize_xof()
+            });
+            let mut blocks = [[0u8; RATE]; 4];
+            for _ in 0..3 {
+                xof.squeeze_block(&mut blocks);
+                for (block, reader) in blocks.iter().zip(&mut readers) {
+                    let mut expected = [0u8; RATE];
+                    reader.read(&mut expected);
+                    assert_eq!(*block, expected, "input length {len}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_shake128_x4_matches_sha3() {
+        squeeze_matches_sha3::<SHAKE128_RATE, Shake128>();
+    }
+
+    #[test]
+    fn test_shake256_x4_matches_sha3() {
+        squeeze_matches_sha3::<SHAKE256_RATE, Shake256>();
+    }
+}
+
@@ -0,0 +1,64 @@
+use core::convert::Infallible;
+
+use kem::{Decapsulate, Encapsulate};
+use rand_core_06::CryptoRngCore;
+use zeroize::Zeroizing;
+
+use crate::{Error, params::ParameterSet, typed::{CipherText, DecapsKey, EncapsKey, MlKem}, types::SharedSecretKey};
+
+impl<P: ParameterSet> Encapsulate<CipherText<P>, SharedSecretKey> for EncapsKey<P> {
+    type Error = Error;
+
+    fn encapsulate(&self, rng: &mut impl CryptoRngCore) -> Result<(CipherText<P>, SharedSecretKey), Error> {
+        let mut m = Zeroizing::new([0u8; 32]);
+        rng.try_fill_bytes(&mut m[..]).map_err(|_| Error::RandomnessFailure)?;
+
+        let (ssk, c) = MlKem::<P>::new().encaps_from_message(self, &m);
+        Ok((c, ssk))
+    }
+}
+
+// The key was checked on construction and implicit rejection never fails
+impl<P: ParameterSet> Decapsulate<CipherText<P>, SharedSecretKey> for DecapsKey<P> {
+    type Error = Infallible;
+
+    fn decapsulate(&self, encapsulated_key: &CipherText<P>) -> Result<SharedSecretKey, Infallible> {
+        Ok(MlKem::<P>::new().decaps(self, encapsulated_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{MlKem512, MlKem768, MlKem1024};
+    use rand_chacha_03::ChaCha20Rng;
+    use rand_core_06::SeedableRng;
+
+    // Written against the traits only, as code generic over KEMs would be
+    fn kem_roundtrip<EK, DK, C, SS>(ek: &EK, dk: &DK, rng: &mut impl CryptoRngCore) -> (SS, SS)
+    where
+        EK: Encapsulate<C, SS>,
+        DK: Decapsulate<C, SS>,
+    {
+        let (c, ss_enc) = ek.encapsulate(rng).expect("encapsulation should not fail");
+        let ss_dec = dk.decapsulate(&c).expect("decapsulation should not fail");
+        (ss_enc, ss_dec)
+    }
+
+    fn check<P: ParameterSet>(seed: u64) {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let mut key_seed = [0u8; 64];
+        rand_core_06::RngCore::fill_bytes(&mut rng, &mut key_seed);
+        let (ek, dk) = MlKem::<P>::new().keygen_from_seed(&key_seed.into());
+
+        let (ss_enc, ss_dec) = kem_roundtrip(&ek, &dk, &mut rng);
+        assert_eq!(ss_enc.into_bytes(), ss_dec.into_bytes());
+    }
+
+    #[test]
+    fn test_kem_traits_roundtrip() {
+        check::<MlKem512>(1);
+        check::<MlKem768>(2);
+        check::<MlKem1024>(3);
+    }
+}
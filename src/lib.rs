@@ -1,27 +1,124 @@
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
 mod converter;
 mod sample;
 mod ntt;
 mod arithmetic;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2;
 mod hash_utils;
+mod keccak;
 mod k_pke;
 mod ml_kem_internal;
+mod error;
+#[cfg(test)]
+mod acvp;
+#[cfg(all(test, target_os = "linux"))]
+mod dudect;
+#[cfg(feature = "kem")]
+mod kem_traits;
+#[cfg(feature = "serde")]
+mod serialization;
 
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "hazmat")]
+pub mod hazmat;
+#[cfg(feature = "hpke")]
+pub mod hpke;
+pub mod params;
+#[cfg(feature = "pkcs8")]
+pub mod pkcs8;
+#[cfg(feature = "sealed-box")]
+pub mod sealed_box;
+pub mod typed;
 pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "xwing")]
+pub mod xwing;
+
+pub use error::Error;
+pub use params::{MlKem512, MlKem768, MlKem1024, ParameterSet};
 
 pub use rand_core::{TryCryptoRng, TryRngCore};
-#[cfg(feature = "default-rng")]
-use rand_core::{OsRng, OsError};
+#[cfg(all(feature = "default-rng", feature = "alloc"))]
+use rand_core::OsRng;
 
-use crate::{ml_kem_internal::{ml_kem_decaps_internal, ml_kem_encaps_internal, ml_kem_keygen_internal}, types::{CipherText, DecapsKey, EncapsKey, SharedSecretKey}};
+#[cfg(feature = "alloc")]
+use alloc::vec;
+#[cfg(feature = "alloc")]
+use zeroize::Zeroizing;
+#[cfg(feature = "alloc")]
+use crate::{ml_kem_internal::{PreparedDk, PreparedEk, check_ciphertext, check_decaps_key, check_encaps_key, ml_kem_decaps_internal, ml_kem_decaps_prepared,
+ml_kem_encaps_internal, ml_kem_encaps_prepared, ml_kem_keygen_internal}, types::{CipherText, DecapsKey, DecapsKeySeed, EncapsKey, PreparedDecapsKey, PreparedEncapsKey, SharedSecretKey}};
 
-const Q: u16 = 3329;
+const Q: i16 = 3329;
+// Largest k over the parameter sets, used to size polynomial vectors on the stack
+const MAX_K: usize = 4;
+// Ciphertext length for ML-KEM-1024, the longest over the parameter sets
+const MAX_CIPHERTEXT_LEN: usize = 1568;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MlKemParams {
+    #[cfg_attr(feature = "serde", serde(rename = "ML-KEM-512"))]
     MlKem512,
+    #[cfg_attr(feature = "serde", serde(rename = "ML-KEM-768"))]
     MlKem768,
+    #[cfg_attr(feature = "serde", serde(rename = "ML-KEM-1024"))]
     MlKem1024
 }
 
+// Untyped keys carry their parameter set only in their length
+impl MlKemParams {
+    /// The module rank k from FIPS 203 Table 2.
+    pub const fn k(self) -> usize {
+        match self {
+            MlKemParams::MlKem512 => 2,
+            MlKemParams::MlKem768 => 3,
+            MlKemParams::MlKem1024 => 4,
+        }
+    }
+
+    /// Length in bytes of an encapsulation key, 384k + 32.
+    pub const fn encaps_key_len(self) -> usize {
+        384 * self.k() + 32
+    }
+
+    /// Length in bytes of an expanded decapsulation key, 768k + 96.
+    pub const fn decaps_key_len(self) -> usize {
+        768 * self.k() + 96
+    }
+
+    /// Length in bytes of a ciphertext, 32(du k + dv).
+    pub const fn ciphertext_len(self) -> usize {
+        match self {
+            MlKemParams::MlKem512 => 768,
+            MlKemParams::MlKem768 => 1088,
+            MlKemParams::MlKem1024 => 1568,
+        }
+    }
+
+    /// The parameter set whose encapsulation keys are `len` bytes long.
+    pub fn from_encaps_key_len(len: usize) -> Option<Self> {
+        [MlKemParams::MlKem512, MlKemParams::MlKem768, MlKemParams::MlKem1024].into_iter().find(|params| params.encaps_key_len() == len)
+    }
+
+    /// The parameter set whose expanded decapsulation keys are `len` bytes long.
+    pub fn from_decaps_key_len(len: usize) -> Option<Self> {
+        [MlKemParams::MlKem512, MlKemParams::MlKem768, MlKemParams::MlKem1024].into_iter().find(|params| params.decaps_key_len() == len)
+    }
+}
+
+// Runtime parameter set selection with heap allocated keys and ciphertexts,
+// use typed::MlKem to avoid the allocator
+#[cfg(feature = "alloc")]
 pub struct MlKem {
     k: usize,
     eta1: u8,
@@ -30,6 +127,7 @@ pub struct MlKem {
     dv: u8
 }
 
+#[cfg(feature = "alloc")]
 impl MlKem {
     pub fn new(params: MlKemParams) -> Self {
         match params {
@@ -57,38 +155,136 @@ impl MlKem {
         }
     }
 
-    pub fn keygen_with_rng<R: TryCryptoRng + TryRngCore>(&self, rng: &mut R) -> Result<(EncapsKey, DecapsKey), R::Error> {
-        let mut d = [0u8; 32];
-        let mut z = [0u8; 32];
-        rng.try_fill_bytes(&mut d)?;
-        rng.try_fill_bytes(&mut z)?;
+    pub fn keygen_with_rng<R: TryCryptoRng + TryRngCore>(&self, rng: &mut R) -> Result<(EncapsKey, DecapsKey), Error> {
+        let seed = DecapsKeySeed::generate_with_rng(rng)?;
 
-        Ok(ml_kem_keygen_internal(self.k, self.eta1, &d, &z))
+        Ok(self.keygen_from_seed(&seed))
     }
 
-    pub fn encaps_with_rng<R: TryCryptoRng + TryRngCore>(&self, ek: &EncapsKey, rng: &mut R) -> Result<(SharedSecretKey, CipherText), R::Error> {
-        let mut m = [0u8; 32];
-        rng.try_fill_bytes(&mut m)?;
+    // Deterministically expands the seed (d, z) into the key pair
+    pub fn keygen_from_seed(&self, seed: &DecapsKeySeed) -> (EncapsKey, DecapsKey) {
+        let mut ek = vec![0u8; 384 * self.k + 32];
+        let mut dk = vec![0u8; 768 * self.k + 96];
+        ml_kem_keygen_internal(self.k, self.eta1, seed.d(), seed.z(), &mut ek, &mut dk);
+
+        (EncapsKey(ek), DecapsKey(dk))
+    }
+
+    pub fn encaps_with_rng<R: TryCryptoRng + TryRngCore>(&self, ek: &EncapsKey, rng: &mut R) -> Result<(SharedSecretKey, CipherText), Error> {
+        if !check_encaps_key(self.k, &ek.0) {
+            return Err(Error::InvalidEncapsKey);
+        }
+
+        let mut m = Zeroizing::new([0u8; 32]);
+        rng.try_fill_bytes(&mut m[..]).map_err(|_| Error::RandomnessFailure)?;
+
+        Ok(self.encaps_from_message(ek, &m))
+    }
+
+    /// ML-KEM.Encaps_internal from FIPS 203, with the message `m` supplied by the caller.
+    ///
+    /// Only for known-answer testing, `m` must otherwise be fresh randomness.
+    #[cfg(any(test, feature = "hazmat"))]
+    pub fn encaps_deterministic(&self, ek: &EncapsKey, m: &[u8; 32]) -> Result<(SharedSecretKey, CipherText), Error> {
+        self.encaps_checked(ek, m)
+    }
+
+    // Checks ek and encapsulates the message m the caller drew from its RNG
+    #[cfg(any(test, feature = "hazmat", feature = "hpke"))]
+    pub(crate) fn encaps_checked(&self, ek: &EncapsKey, m: &[u8; 32]) -> Result<(SharedSecretKey, CipherText), Error> {
+        if !check_encaps_key(self.k, &ek.0) {
+            return Err(Error::InvalidEncapsKey);
+        }
+
+        Ok(self.encaps_from_message(ek, m))
+    }
+
+    fn encaps_from_message(&self, ek: &EncapsKey, m: &[u8; 32]) -> (SharedSecretKey, CipherText) {
+        let mut c = vec![0u8; 32 * (self.du as usize * self.k + self.dv as usize)];
+        let ssk = ml_kem_encaps_internal(self.k, self.eta1, self.eta2, self.du, self.dv, &ek.0, m, &mut c);
+
+        (ssk, CipherText(c))
+    }
+
+    // The caller has checked dk and the length of c
+    #[cfg(feature = "xwing")]
+    pub(crate) fn decaps_unchecked(&self, dk: &DecapsKey, c: &[u8]) -> SharedSecretKey {
+        ml_kem_decaps_internal(self.k, self.eta1, self.eta2, self.du, self.dv, &dk.0, c)
+    }
+
+    pub fn decaps(&self, dk: &DecapsKey, c: &CipherText) -> Result<SharedSecretKey, Error> {
+        if !check_ciphertext(self.k, self.du, self.dv, &c.0) {
+            return Err(Error::InvalidCipherText);
+        }
+        if !check_decaps_key(self.k, &dk.0) {
+            return Err(Error::InvalidDecapsKey);
+        }
 
-        Ok(ml_kem_encaps_internal(self.k, self.eta1, self.eta2, self.du, self.dv, ek, &m))
+        Ok(ml_kem_decaps_internal(self.k, self.eta1, self.eta2, self.du, self.dv, &dk.0, &c.0))
     }
 
-    pub fn decaps(&self, dk: &DecapsKey, c: &CipherText) -> SharedSecretKey {
-        ml_kem_decaps_internal(self.k, self.eta1, self.eta2, self.du, self.dv, dk, c)
+    /// Checks and parses the encapsulation key once, so that repeated encapsulations
+    /// skip decoding t and sampling the matrix A.
+    pub fn prepare_encaps_key(&self, ek: &EncapsKey) -> Result<PreparedEncapsKey, Error> {
+        if !check_encaps_key(self.k, &ek.0) {
+            return Err(Error::InvalidEncapsKey);
+        }
+
+        Ok(PreparedEncapsKey { k: self.k, ek: PreparedEk::new(self.k, &ek.0) })
+    }
+
+    /// Checks and parses the decapsulation key once, so that repeated decapsulations
+    /// skip decoding s and t and sampling the matrix A.
+    pub fn prepare_decaps_key(&self, dk: &DecapsKey) -> Result<PreparedDecapsKey, Error> {
+        if !check_decaps_key(self.k, &dk.0) {
+            return Err(Error::InvalidDecapsKey);
+        }
+
+        Ok(PreparedDecapsKey { k: self.k, dk: PreparedDk::new(self.k, &dk.0) })
+    }
+
+    pub fn encaps_prepared_with_rng<R: TryCryptoRng + TryRngCore>(&self, ek: &PreparedEncapsKey, rng: &mut R) -> Result<(SharedSecretKey, CipherText), Error> {
+        if ek.k != self.k {
+            return Err(Error::InvalidEncapsKey);
+        }
+
+        let mut m = Zeroizing::new([0u8; 32]);
+        rng.try_fill_bytes(&mut m[..]).map_err(|_| Error::RandomnessFailure)?;
+
+        let mut c = vec![0u8; 32 * (self.du as usize * self.k + self.dv as usize)];
+        let ssk = ml_kem_encaps_prepared(self.k, self.eta1, self.eta2, self.du, self.dv, &ek.ek, &m, &mut c);
+
+        Ok((ssk, CipherText(c)))
+    }
+
+    pub fn decaps_prepared(&self, dk: &PreparedDecapsKey, c: &CipherText) -> Result<SharedSecretKey, Error> {
+        if !check_ciphertext(self.k, self.du, self.dv, &c.0) {
+            return Err(Error::InvalidCipherText);
+        }
+        if dk.k != self.k {
+            return Err(Error::InvalidDecapsKey);
+        }
+
+        Ok(ml_kem_decaps_prepared(self.k, self.eta1, self.eta2, self.du, self.dv, &dk.dk, &c.0))
     }
 
     #[cfg(feature = "default-rng")]
-    pub fn keygen(&self) -> Result<(EncapsKey, DecapsKey), OsError> {
+    pub fn keygen(&self) -> Result<(EncapsKey, DecapsKey), Error> {
         self.keygen_with_rng(&mut OsRng)
     }
 
     #[cfg(feature = "default-rng")]
-    pub fn encaps(&self, ek: &EncapsKey) -> Result<(SharedSecretKey, CipherText), OsError> {
+    pub fn encaps(&self, ek: &EncapsKey) -> Result<(SharedSecretKey, CipherText), Error> {
         self.encaps_with_rng(ek, &mut OsRng)
     }
+
+    #[cfg(feature = "default-rng")]
+    pub fn encaps_prepared(&self, ek: &PreparedEncapsKey) -> Result<(SharedSecretKey, CipherText), Error> {
+        self.encaps_prepared_with_rng(ek, &mut OsRng)
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
     use rand_chacha::ChaCha20Rng;
@@ -102,7 +298,7 @@ mod tests {
         // If your API returns plain values (no Result):
         let (ek, dk) = kem.keygen_with_rng(&mut rng).expect("random generation for key should not fail");
         let (ssk_enc, ct) = kem.encaps_with_rng(&ek, &mut rng).expect("random generation for the encapsulation should not fail");
-        let ssk_dec = kem.decaps(&dk, &ct);
+        let ssk_dec = kem.decaps(&dk, &ct).expect("decapsulation of a valid ciphertext should not fail");
 
         assert_eq!(ssk_enc.into_bytes(), ssk_dec.into_bytes());
     }
@@ -117,8 +313,127 @@ mod tests {
         let dk_bytes = dk.into_bytes();
         let create_dk = DecapsKey::from_slice(dk_bytes.as_slice());
         let (ssk_enc, ct) = kem.encaps_with_rng(&ek, &mut rng).expect("random generation for the encapsulation should not fail");
-        let ssk_dec = kem.decaps(&create_dk, &ct);
+        let ssk_dec = kem.decaps(&create_dk, &ct).expect("decapsulation of a valid ciphertext should not fail");
 
         assert_eq!(ssk_enc.into_bytes(), ssk_dec.into_bytes());
     }
+
+    #[test]
+    fn test_keygen_from_seed() {
+        let kem = MlKem::new(MlKemParams::MlKem512);
+
+        let (ek, dk) = kem.keygen_with_rng(&mut ChaCha20Rng::seed_from_u64(777)).expect("random generation for key should not fail");
+        let seed = DecapsKeySeed::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(777)).expect("random generation for seed should not fail");
+        let stored = DecapsKeySeed::from(*seed.into_bytes());
+        let (seed_ek, seed_dk) = kem.keygen_from_seed(&stored);
+
+        assert_eq!(ek.into_bytes(), seed_ek.into_bytes());
+        assert_eq!(dk.into_bytes(), seed_dk.into_bytes());
+    }
+
+    #[test]
+    fn test_encaps_deterministic() {
+        let kem = MlKem::new(MlKemParams::MlKem1024);
+        let (ek, dk) = kem.keygen_from_seed(&DecapsKeySeed::from([42u8; 64]));
+
+        let mut rng = ChaCha20Rng::seed_from_u64(1111);
+        let (ssk_rng, ct_rng) = kem.encaps_with_rng(&ek, &mut rng).expect("random generation for the encapsulation should not fail");
+        let mut m = [0u8; 32];
+        ChaCha20Rng::seed_from_u64(1111).try_fill_bytes(&mut m).expect("random generation should not fail");
+        let (ssk, ct) = kem.encaps_deterministic(&ek, &m).expect("encapsulation key should be valid");
+
+        assert_eq!(ssk_rng.into_bytes(), ssk.clone().into_bytes());
+        assert_eq!(ct_rng.into_bytes(), ct.clone().into_bytes());
+        assert_eq!(kem.decaps(&dk, &ct).expect("decapsulation of a valid ciphertext should not fail").into_bytes(), ssk.into_bytes());
+    }
+
+    #[test]
+    fn test_prepared_keys() {
+        let kem = MlKem::new(MlKemParams::MlKem768);
+        let (ek, dk) = kem.keygen_with_rng(&mut ChaCha20Rng::seed_from_u64(5150)).expect("random generation for key should not fail");
+        let prepared_ek = kem.prepare_encaps_key(&ek).expect("encapsulation key should be valid");
+        let prepared_dk = kem.prepare_decaps_key(&dk).expect("decapsulation key should be valid");
+
+        for seed in 0..4 {
+            // The prepared path matches the one-shot path for the same randomness
+            let (ssk, ct) = kem.encaps_prepared_with_rng(&prepared_ek, &mut ChaCha20Rng::seed_from_u64(seed)).expect("random generation for the encapsulation should not fail");
+            let (expected_ssk, expected_ct) = kem.encaps_with_rng(&ek, &mut ChaCha20Rng::seed_from_u64(seed)).expect("random generation for the encapsulation should not fail");
+            assert_eq!(ct.clone().into_bytes(), expected_ct.into_bytes());
+            assert_eq!(ssk.clone().into_bytes(), expected_ssk.into_bytes());
+
+            let ssk_dec = kem.decaps_prepared(&prepared_dk, &ct).expect("decapsulation of a valid ciphertext should not fail");
+            assert_eq!(ssk_dec.into_bytes(), ssk.into_bytes());
+        }
+
+        // Prepared keys are tied to the parameter set they were prepared for
+        let kem_512 = MlKem::new(MlKemParams::MlKem512);
+        assert_eq!(kem_512.encaps_prepared_with_rng(&prepared_ek, &mut ChaCha20Rng::seed_from_u64(0)).err(), Some(Error::InvalidEncapsKey));
+        assert_eq!(kem_512.decaps_prepared(&prepared_dk, &CipherText::from(vec![0u8; 768])).err(), Some(Error::InvalidDecapsKey));
+        assert_eq!(kem_512.prepare_encaps_key(&ek).err(), Some(Error::InvalidEncapsKey));
+    }
+
+    #[test]
+    fn test_encaps_rejects_invalid_keys() {
+        let mut rng = ChaCha20Rng::seed_from_u64(2024);
+        let kem = MlKem::new(MlKemParams::MlKem512);
+
+        let (ek, _) = kem.keygen_with_rng(&mut rng).expect("random generation for key should not fail");
+        let ek_bytes = ek.into_bytes();
+
+        // Set the first 12-bit coefficient to q, which byte_decode would silently reduce to 0
+        let mut unreduced = ek_bytes.clone();
+        unreduced[0] = (Q & 0xFF) as u8;
+        unreduced[1] = (unreduced[1] & 0xF0) | (Q >> 8) as u8;
+        assert_eq!(kem.encaps_with_rng(&EncapsKey::from(unreduced), &mut rng).err(), Some(Error::InvalidEncapsKey));
+
+        let truncated = EncapsKey::from_slice(&ek_bytes[..ek_bytes.len() - 1]);
+        assert_eq!(kem.encaps_with_rng(&truncated, &mut rng).err(), Some(Error::InvalidEncapsKey));
+
+        // Keys from a different parameter set fail the type check
+        let kem_768 = MlKem::new(MlKemParams::MlKem768);
+        assert_eq!(kem_768.encaps_with_rng(&EncapsKey::from(ek_bytes), &mut rng).err(), Some(Error::InvalidEncapsKey));
+    }
+
+    #[test]
+    fn test_decaps_rejects_invalid_inputs() {
+        let mut rng = ChaCha20Rng::seed_from_u64(4048);
+        let kem = MlKem::new(MlKemParams::MlKem1024);
+
+        let (ek, dk) = kem.keygen_with_rng(&mut rng).expect("random generation for key should not fail");
+        let (_, ct) = kem.encaps_with_rng(&ek, &mut rng).expect("random generation for the encapsulation should not fail");
+        let dk_bytes = dk.into_bytes();
+        let ct_bytes = ct.into_bytes();
+
+        // Flipping a bit of the stored hash H(ek) fails the hash check
+        let mut bad_hash = dk_bytes.clone();
+        bad_hash[768 * 4 + 32] ^= 1;
+        assert_eq!(kem.decaps(&DecapsKey::from_slice(&bad_hash), &CipherText::from_slice(&ct_bytes)).err(), Some(Error::InvalidDecapsKey));
+
+        let truncated_dk = DecapsKey::from_slice(&dk_bytes[..dk_bytes.len() - 1]);
+        assert_eq!(kem.decaps(&truncated_dk, &CipherText::from_slice(&ct_bytes)).err(), Some(Error::InvalidDecapsKey));
+
+        let truncated_ct = CipherText::from_slice(&ct_bytes[..ct_bytes.len() - 1]);
+        assert_eq!(kem.decaps(&DecapsKey::from_slice(&dk_bytes), &truncated_ct).err(), Some(Error::InvalidCipherText));
+
+        let mut oversized_ct = ct_bytes;
+        oversized_ct.push(0);
+        assert_eq!(kem.decaps(&DecapsKey::from_slice(&dk_bytes), &CipherText::from(oversized_ct)).err(), Some(Error::InvalidCipherText));
+    }
+
+    #[test]
+    fn test_params_lengths() {
+        for params in [MlKemParams::MlKem512, MlKemParams::MlKem768, MlKemParams::MlKem1024] {
+            let kem = MlKem::new(params);
+            let (ek, dk) = kem.keygen_from_seed(&DecapsKeySeed::from([7u8; 64]));
+            let (_, ct) = kem.encaps_deterministic(&ek, &[9u8; 32]).unwrap();
+
+            assert_eq!(ek.0.len(), params.encaps_key_len());
+            assert_eq!(dk.0.len(), params.decaps_key_len());
+            assert_eq!(ct.0.len(), params.ciphertext_len());
+            assert_eq!(MlKemParams::from_encaps_key_len(ek.0.len()), Some(params));
+            assert_eq!(MlKemParams::from_decaps_key_len(dk.0.len()), Some(params));
+        }
+        assert_eq!(MlKemParams::from_encaps_key_len(1088), None);
+        assert_eq!(MlKemParams::from_decaps_key_len(1184), None);
+    }
 }
\ No newline at end of file
@@ -1,41 +1,118 @@
-use crate::{hash_utils::{g, h, j}, k_pke::{pke_decrypt, pke_encrypt, pke_key_gen}, types::{CipherText, DecapsKey, EncapsKey, SharedSecretKey}};
+use crate::{MAX_CIPHERTEXT_LEN, converter::{byte_decode, byte_encode}, hash_utils::{g, h, j},
+k_pke::{PkeDecryptionKey, PkeEncryptionKey, pke_decrypt_prepared, pke_encrypt_prepared, pke_key_gen}, types::SharedSecretKey};
 use subtle::{ConditionallySelectable, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
-pub(crate) fn ml_kem_keygen_internal(k: usize, eta1: u8, d: &[u8; 32], z: &[u8; 32]) -> (EncapsKey, DecapsKey) {
-    let (ek_pke, dk_pke) = pke_key_gen(k, eta1, d);
+// ek must be 384k + 32 bytes and dk must be 768k + 96 bytes
+pub(crate) fn ml_kem_keygen_internal(k: usize, eta1: u8, d: &[u8; 32], z: &[u8; 32], ek: &mut [u8], dk: &mut [u8]) {
+    debug_assert!(dk.len() == 768 * k + 96, "Decapsulation key must be 768k + 96 bytes");
+
+    pke_key_gen(k, eta1, d, ek, &mut dk[0 .. 384 * k]);
 
     // 384k + 384k + 32 + 32
-    let mut dk = Vec::with_capacity(768 * k + 64);
-    dk.extend_from_slice(&dk_pke);
-    dk.extend_from_slice(&ek_pke);
-    dk.extend_from_slice(&h(&ek_pke));
-    dk.extend_from_slice(z);
+    dk[384 * k .. 768 * k + 32].copy_from_slice(ek);
+    dk[768 * k + 32 .. 768 * k + 64].copy_from_slice(&h(ek));
+    dk[768 * k + 64 .. 768 * k + 96].copy_from_slice(z);
+}
+
+// Encapsulation key with the K-PKE key parsed and H(ek) computed ahead of time
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct PreparedEk {
+    ek_pke: PkeEncryptionKey,
+    h: [u8; 32],
+}
+
+impl PreparedEk {
+    pub(crate) fn new(k: usize, ek: &[u8]) -> Self {
+        PreparedEk { ek_pke: PkeEncryptionKey::new(k, ek), h: h(ek) }
+    }
+}
+
+// Decapsulation key with both K-PKE keys parsed ahead of time
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct PreparedDk {
+    dk_pke: PkeDecryptionKey,
+    ek: PreparedEk,
+    z: [u8; 32],
+}
+
+impl PreparedDk {
+    pub(crate) fn new(k: usize, dk: &[u8]) -> Self {
+        let dk_pke = &dk[0 .. 384 * k];
+        let ek_pke = &dk[384 * k .. 768 * k + 32];
+        let h = &dk[768 * k + 32 .. 768 * k + 64];
+        let z = &dk[768 * k + 64 .. 768 * k + 96];
+
+        PreparedDk {
+            dk_pke: PkeDecryptionKey::new(k, dk_pke),
+            ek: PreparedEk { ek_pke: PkeEncryptionKey::new(k, ek_pke), h: h.try_into().unwrap() },
+            z: z.try_into().unwrap(),
+        }
+    }
+}
 
-    (EncapsKey(ek_pke), DecapsKey(dk))
+// c must be 32(du * k + dv) bytes
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn ml_kem_encaps_internal(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, ek: &[u8], m: &[u8; 32], c: &mut [u8]) -> SharedSecretKey {
+    ml_kem_encaps_prepared(k, eta1, eta2, du, dv, &PreparedEk::new(k, ek), m, c)
 }
 
-pub(crate) fn ml_kem_encaps_internal(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, ek: &EncapsKey, m: &[u8; 32]) -> (SharedSecretKey, CipherText) {
-    let (k_cap, r) = g(m, &h(&ek.0));
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn ml_kem_encaps_prepared(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, ek: &PreparedEk, m: &[u8; 32], c: &mut [u8]) -> SharedSecretKey {
+    let (k_cap, r) = g(m, &ek.h);
+    let r = Zeroizing::new(r);
+
+    pke_encrypt_prepared(k, eta1, eta2, du, dv, &ek.ek_pke, m, &r, c);
 
-    let c = pke_encrypt(k, eta1, eta2, du, dv, &ek.0, m, &r);
+    SharedSecretKey(k_cap)
+}
 
-    (SharedSecretKey(k_cap), CipherText(c))
+pub(crate) fn ml_kem_decaps_internal(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, dk: &[u8], c: &[u8]) -> SharedSecretKey {
+    ml_kem_decaps_prepared(k, eta1, eta2, du, dv, &PreparedDk::new(k, dk), c)
 }
 
-pub(crate) fn ml_kem_decaps_internal(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, dk: &DecapsKey, c: &CipherText) -> SharedSecretKey {
-    let dk_pke = &dk.0[0 .. 384 * k];
-    let ek_pke = &dk.0[384 * k .. 768 * k + 32];
-    let h = &dk.0[768 * k + 32 .. 768 * k + 64];
-    let z = &dk.0[768 * k + 64 .. 768 * k + 96];
+pub(crate) fn ml_kem_decaps_prepared(k: usize, eta1: u8, eta2: u8, du: u8, dv: u8, dk: &PreparedDk, c: &[u8]) -> SharedSecretKey {
+    let m = pke_decrypt_prepared(k, du, dv, &dk.dk_pke, c);
 
-    let m = pke_decrypt(k, du, dv, dk_pke, &c.0);
+    let (mut k_prime, r_prime) = g(m.as_ref(), &dk.ek.h);
+    let r_prime = Zeroizing::new(r_prime);
 
-    let (mut k_prime, r_prime) = g(&m, h);
-    
-    let k_bar = j(z, &c.0);
+    let k_bar = Zeroizing::new(j(&dk.z, c));
 
-    let c_prime = pke_encrypt(k, eta1, eta2, du, dv, ek_pke, m.as_slice().try_into().unwrap(), &r_prime);
+    // The re-encryption of m is only public when it matches c
+    let mut c_prime = Zeroizing::new([0u8; MAX_CIPHERTEXT_LEN]);
+    let c_prime = &mut c_prime[..c.len()];
+    pke_encrypt_prepared(k, eta1, eta2, du, dv, &dk.ek.ek_pke, &m, &r_prime, c_prime);
 
-    k_prime.conditional_assign(&k_bar, c.0.ct_ne(&c_prime));
+    k_prime.conditional_assign(&k_bar, c.ct_ne(c_prime));
     SharedSecretKey(k_prime)
-}
\ No newline at end of file
+}
+
+// Encapsulation key check from FIPS 203 §7.2
+// Type check on the length and modulus check that every coefficient is below q
+pub(crate) fn check_encaps_key(k: usize, ek: &[u8]) -> bool {
+    if ek.len() != 384 * k + 32 {
+        return false;
+    }
+    let mut encoded = [0u8; 384];
+    ek[..384 * k].chunks_exact(384).all(|chunk| {
+        byte_encode(&byte_decode(chunk, 12), 12, &mut encoded);
+        encoded == chunk
+    })
+}
+
+// Decapsulation key check from FIPS 203 §7.3
+// Type check on the length and hash check on the embedded encapsulation key
+pub(crate) fn check_decaps_key(k: usize, dk: &[u8]) -> bool {
+    if dk.len() != 768 * k + 96 {
+        return false;
+    }
+    h(&dk[384 * k .. 768 * k + 32]) == dk[768 * k + 32 .. 768 * k + 64]
+}
+
+// Ciphertext type check from FIPS 203 §7.3
+// Typed ciphertexts have their length fixed by the parameter set
+#[cfg(feature = "alloc")]
+pub(crate) fn check_ciphertext(k: usize, du: u8, dv: u8, c: &[u8]) -> bool {
+    c.len() == 32 * (du as usize * k + dv as usize)
+}
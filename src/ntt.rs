@@ -1,6 +1,12 @@
-use crate::arithmetic::{mod_q_add, mod_q_mul, mod_q_sub};
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use crate::avx2;
+use crate::{MAX_K, arithmetic::{R2, barrett_reduce, caddq, fqmul, mod_q_add, mod_q_sub, montgomery_reduce, reduce, to_mont}};
 
-const ZETA_BITREV7: [u16; 128] = [
+// Fixed size storage for up to MAX_K polynomials, only the first k entries are used
+pub(crate) type PolyVec = [[i16; 256]; MAX_K];
+pub(crate) type PolyMatrix = [PolyVec; MAX_K];
+
+const ZETA_BITREV7: [i16; 128] = [
     1, 1729, 2580, 3289, 2642,  630, 1897,  848, 1062, 1919,  193,  797, 2786, 3260,  569, 1746,
   296, 2447, 1339, 1476, 3046,   56, 2240, 1333, 1426, 2094,  535, 2882, 2393, 2879, 1974,  821,
   289,  331, 3253, 1756, 1197, 2304, 2277, 2055,  650, 1977, 2513,  632, 2865,   33, 1320, 1915,
@@ -11,85 +17,169 @@ const ZETA_BITREV7: [u16; 128] = [
  2804, 1092,  403, 1026, 1143, 2150, 2775,  886, 1722, 1212, 1874, 1029, 2110, 2935,  885, 2154,
 ];
 
-pub(crate) fn ntt(f: &[u16; 256]) -> [u16; 256] {
+const ZETA_2BITREV71: [i16; 128] = [
+    17, 3312, 2761,  568,  583, 2746, 2649,  680, 1637, 1692,  723, 2606, 2288, 1041, 1100, 2229, 
+  1409, 1920, 2662,  667, 3281,   48,  233, 3096,  756, 2573, 2156, 1173, 3015,  314, 3050,  279, 
+  1703, 1626, 1651, 1678, 2789,  540, 1789, 1540, 1847, 1482,  952, 2377, 1461, 1868, 2687,  642, 
+   939, 2390, 2308, 1021, 2437,  892, 2388,  941,  733, 2596, 2337,  992,  268, 3061,  641, 2688, 
+  1584, 1745, 2298, 1031, 2037, 1292, 3220,  109,  375, 2954, 2549,  780, 2090, 1239, 1645, 1684, 
+  1063, 2266,  319, 3010, 2773,  556,  757, 2572, 2099, 1230,  561, 2768, 2466,  863, 2594,  735, 
+  2804,  525, 1092, 2237,  403, 2926, 1026, 2303, 1143, 2186, 2150, 1179, 2775,  554,  886, 2443, 
+  1722, 1607, 1212, 2117, 1874, 1455, 1029, 2300, 2110, 1219, 2935,  394,  885, 2444, 2154, 1175,
+];
+
+// The zeta tables in the Montgomery domain, so that fqmul by an entry multiplies by the zeta itself
+pub(crate) const ZETAS_MONT: [i16; 128] = mont_table(&ZETA_BITREV7);
+pub(crate) const GAMMAS_MONT: [i16; 128] = mont_table(&ZETA_2BITREV71);
+// 128^-1 mod q, scaling the output of the inverse NTT
+pub(crate) const NTT_INV_SCALE: i16 = to_mont(3303);
+
+// Runs the AVX2 version of a function instead of the portable one when the CPU supports it
+macro_rules! dispatch {
+    ($avx2:expr) => {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        if avx2::enabled() {
+            // SAFETY: the CPU supports AVX2, the only target feature the function enables
+            return unsafe { $avx2 };
+        }
+    };
+}
+
+const fn mont_table(zetas: &[i16; 128]) -> [i16; 128] {
+    let mut table = [0i16; 128];
+    let mut i = 0;
+    while i < 128 {
+        table[i] = to_mont(zetas[i]);
+        i += 1;
+    }
+    table
+}
+
+pub(crate) fn ntt(f: &[i16; 256]) -> [i16; 256] {
+    dispatch!(avx2::ntt(f));
+    ntt_scalar(f)
+}
+
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn ntt_scalar(f: &[i16; 256]) -> [i16; 256] {
     let mut f_ntt = *f;
     let mut k = 1;
 
+    // Every layer adds less than q to the coefficients, leaving them below 8q before the final reduction
     let mut len = 128;
     while len >= 2 {
         for start in (0..256).step_by(2 * len) {
-            let zeta = ZETA_BITREV7[k];
+            let zeta = ZETAS_MONT[k];
             k += 1;
-            for j in start..start + len {
-                let t = mod_q_mul(zeta, f_ntt[j + len]);
-                f_ntt[j + len] = mod_q_sub(f_ntt[j], t);
-                f_ntt[j] = mod_q_add(f_ntt[j], t);
+            let (lo, hi) = f_ntt[start..start + 2 * len].split_at_mut(len);
+            for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+                let t = fqmul(zeta, *b);
+                *b = *a - t;
+                *a += t;
             }
         }
         len /= 2;
     }
 
+    for i in 0..256 {
+        f_ntt[i] = reduce(f_ntt[i]);
+    }
     f_ntt
 }
 
-pub(crate) fn ntt_inv(f_ntt: &[u16; 256]) -> [u16; 256] {
+pub(crate) fn ntt_inv(f_ntt: &[i16; 256]) -> [i16; 256] {
+    dispatch!(avx2::ntt_inv(f_ntt));
+    ntt_inv_scalar(f_ntt)
+}
+
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn ntt_inv_scalar(f_ntt: &[i16; 256]) -> [i16; 256] {
     let mut f = *f_ntt;
     let mut k = 127;
 
+    // Sums are Barrett reduced and products Montgomery reduced, keeping every coefficient below q
     let mut len = 2;
     while len <= 128 {
         for start in (0..256).step_by(2 * len) {
-            let zeta = ZETA_BITREV7[k];
+            let zeta = ZETAS_MONT[k];
             k -= 1;
-            for j in start..start + len {
-                let t = f[j];
-                f[j] = mod_q_add(t, f[j + len]);
-                f[j + len] = mod_q_mul(zeta, mod_q_sub(f[j + len], t));
+            let (lo, hi) = f[start..start + 2 * len].split_at_mut(len);
+            for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+                let t = *a;
+                *a = barrett_reduce(t + *b);
+                *b = fqmul(zeta, *b - t);
             }
         }
         len *= 2;
     }
-    
+
     // Multiply every entry by 3303 mod q
     for i in 0..256 {
-        f[i] = mod_q_mul(f[i], 3303);
+        f[i] = caddq(fqmul(f[i], NTT_INV_SCALE));
     }
     f
 }
 
-const ZETA_2BITREV71: [u16; 128] = [
-    17, 3312, 2761,  568,  583, 2746, 2649,  680, 1637, 1692,  723, 2606, 2288, 1041, 1100, 2229, 
-  1409, 1920, 2662,  667, 3281,   48,  233, 3096,  756, 2573, 2156, 1173, 3015,  314, 3050,  279, 
-  1703, 1626, 1651, 1678, 2789,  540, 1789, 1540, 1847, 1482,  952, 2377, 1461, 1868, 2687,  642, 
-   939, 2390, 2308, 1021, 2437,  892, 2388,  941,  733, 2596, 2337,  992,  268, 3061,  641, 2688, 
-  1584, 1745, 2298, 1031, 2037, 1292, 3220,  109,  375, 2954, 2549,  780, 2090, 1239, 1645, 1684, 
-  1063, 2266,  319, 3010, 2773,  556,  757, 2572, 2099, 1230,  561, 2768, 2466,  863, 2594,  735, 
-  2804,  525, 1092, 2237,  403, 2926, 1026, 2303, 1143, 2186, 2150, 1179, 2775,  554,  886, 2443, 
-  1722, 1607, 1212, 2117, 1874, 1455, 1029, 2300, 2110, 1219, 2935,  394,  885, 2444, 2154, 1175,
-];
+// Adds the products of the degree one factors of f_ntt and g_ntt to acc without reducing them.
+// With canonical inputs each call adds less than 2q^2, so MAX_K calls stay within montgomery_reduce's range.
+fn multiply_ntts_acc(f_ntt: &[i16; 256], g_ntt: &[i16; 256], acc: &mut [i32; 256]) {
+    dispatch!(avx2::multiply_ntts_acc(f_ntt, g_ntt, acc));
+    multiply_ntts_acc_scalar(f_ntt, g_ntt, acc)
+}
+
+pub(crate) fn multiply_ntts_acc_scalar(f_ntt: &[i16; 256], g_ntt: &[i16; 256], acc: &mut [i32; 256]) {
+    let pairs = f_ntt.chunks_exact(2).zip(g_ntt.chunks_exact(2)).zip(acc.chunks_exact_mut(2));
+    for (((f, g), acc), &gamma) in pairs.zip(GAMMAS_MONT.iter()) {
+        let g_right_gamma = fqmul(g[1], gamma);
+        acc[0] += f[0] as i32 * g[0] as i32 + f[1] as i32 * g_right_gamma as i32;
+        acc[1] += f[0] as i32 * g[1] as i32 + f[1] as i32 * g[0] as i32;
+    }
+}
+
+// Reduces accumulated products to canonical coefficients, the multiplication by R2 cancels the 2^-16
+// of montgomery_reduce
+fn reduce_acc(acc: &[i32; 256]) -> [i16; 256] {
+    dispatch!(avx2::reduce_acc(acc));
+    reduce_acc_scalar(acc)
+}
 
-pub(crate) fn multiply_ntts(f_ntt: &[u16; 256], g_ntt: &[u16; 256]) -> [u16; 256] {
-    let mut h_ntt = [0u16; 256];
-    for i in 0..128 {
-        let left = 2 * i;
-        let right = 2 * i + 1;
-        h_ntt[left] = mod_q_add(mod_q_mul(f_ntt[left], g_ntt[left]), mod_q_mul(f_ntt[right], mod_q_mul(g_ntt[right], ZETA_2BITREV71[i])));
-        h_ntt[right] = mod_q_add(mod_q_mul(f_ntt[left], g_ntt[right]), mod_q_mul(f_ntt[right], g_ntt[left]));
+pub(crate) fn reduce_acc_scalar(acc: &[i32; 256]) -> [i16; 256] {
+    let mut result = [0i16; 256];
+    for i in 0..256 {
+        result[i] = caddq(fqmul(montgomery_reduce(acc[i]), R2));
     }
-    h_ntt
+    result
+}
+
+// The products of the matrix and vector functions are accumulated before reducing, this is the single product
+#[cfg(any(test, feature = "hazmat"))]
+pub(crate) fn multiply_ntts(f_ntt: &[i16; 256], g_ntt: &[i16; 256]) -> [i16; 256] {
+    let mut acc = [0i32; 256];
+    multiply_ntts_acc(f_ntt, g_ntt, &mut acc);
+    reduce_acc(&acc)
 }
 
 // Arithmetic helper functions
 // Mutates the polynomial a to be the sum of a and b
-pub(crate) fn poly_add(a: &mut [u16; 256], b: &[u16; 256]) {
+pub(crate) fn poly_add(a: &mut [i16; 256], b: &[i16; 256]) {
+    dispatch!(avx2::poly_add(a, b));
+    poly_add_scalar(a, b)
+}
+
+pub(crate) fn poly_add_scalar(a: &mut [i16; 256], b: &[i16; 256]) {
     for i in 0..256 {
         a[i] = mod_q_add(a[i], b[i]);
     }
 }
 
 // Computes the difference of the polynomial vectors a and b
-pub(crate) fn poly_sub(a: &[u16; 256], b: &[u16; 256]) -> [u16; 256] {
-    let mut result = [0u16; 256];
+pub(crate) fn poly_sub(a: &[i16; 256], b: &[i16; 256]) -> [i16; 256] {
+    dispatch!(avx2::poly_sub(a, b));
+    poly_sub_scalar(a, b)
+}
+
+pub(crate) fn poly_sub_scalar(a: &[i16; 256], b: &[i16; 256]) -> [i16; 256] {
+    let mut result = [0i16; 256];
     for i in 0..256 {
         result[i] = mod_q_sub(a[i], b[i]);
     }
@@ -98,57 +188,203 @@ pub(crate) fn poly_sub(a: &[u16; 256], b: &[u16; 256]) -> [u16; 256] {
 
 // Computes the matrix vector multiplication of the matrix of polynomials a
 // and vector of polynomials b
-// Assumes the a matrix is a square matrix
-pub(crate) fn poly_mat_mult(a: &[Vec<[u16; 256]>], b: &[[u16; 256]]) -> Vec<[u16; 256]> {
-    let k = a.len();
-    let mut result = vec![[0u16; 256]; k];
+// Assumes the a matrix is a square matrix of size b.len()
+pub(crate) fn poly_mat_mult(a: &PolyMatrix, b: &[[i16; 256]]) -> PolyVec {
+    let k = b.len();
+    let mut result = [[0i16; 256]; MAX_K];
     for i in 0..k {
+        let mut acc = [0i32; 256];
         for j in 0..k {
-            poly_add(&mut result[i], &multiply_ntts(&a[i][j], &b[j]));
+            multiply_ntts_acc(&a[i][j], &b[j], &mut acc);
         }
+        result[i] = reduce_acc(&acc);
     }
     result
 }
 
 // Computes the matrix vector multiplication of the transpose of the matrix
 // of polynomials a and vector of polynomials
-// Assumes the matrix is a square matrix
-pub(crate) fn poly_mat_transpose_mult(a: &[Vec<[u16; 256]>], b: &[[u16; 256]]) -> Vec<[u16; 256]> {
-    let k = a.len();
-    let mut result = vec![[0u16; 256]; k];
+// Assumes the matrix is a square matrix of size b.len()
+pub(crate) fn poly_mat_transpose_mult(a: &PolyMatrix, b: &[[i16; 256]]) -> PolyVec {
+    let k = b.len();
+    let mut result = [[0i16; 256]; MAX_K];
     for i in 0..k {
+        let mut acc = [0i32; 256];
         for j in 0..k {
-            poly_add(&mut result[i], &multiply_ntts(&a[j][i], &b[j]));
+            multiply_ntts_acc(&a[j][i], &b[j], &mut acc);
         }
+        result[i] = reduce_acc(&acc);
     }
     result
 }
 
-// Computes the sum of the polynomial vectors a and b
-pub(crate) fn poly_vec_add(a: &[[u16; 256]], b: &[[u16; 256]]) -> Vec<[u16; 256]> {
-    let k = a.len();
-    let mut result = vec![[0u16; 256]; k];
-    for i in 0..k {
-        for j in 0..256 {
-            result[i][j] = mod_q_add(a[i][j], b[i][j]);
-        }
+// Mutates the polynomial vector a to be the sum of a and b
+pub(crate) fn poly_vec_add(a: &mut [[i16; 256]], b: &[[i16; 256]]) {
+    for (a_i, b_i) in a.iter_mut().zip(b.iter()) {
+        poly_add(a_i, b_i);
     }
-    result
 }
 
 // Computes the inner product of the polynomial vectors a and b
-pub(crate) fn poly_vec_mult(a: &[[u16; 256]], b: &[[u16; 256]]) -> [u16; 256] {
-    let k = a.len();
-    let mut result = [0u16; 256];
-    for i in 0..k {
-        poly_add(&mut result, &multiply_ntts(&a[i], &b[i]));
+pub(crate) fn poly_vec_mult(a: &[[i16; 256]], b: &[[i16; 256]]) -> [i16; 256] {
+    let mut acc = [0i32; 256];
+    for (a_i, b_i) in a.iter().zip(b.iter()) {
+        multiply_ntts_acc(a_i, b_i, &mut acc);
     }
-    result
+    reduce_acc(&acc)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Q;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{RngCore, SeedableRng};
+    use std::vec::Vec;
+
+    // The previous u16 implementation from FIPS 203 Algorithms 9 to 12 with a % q after every
+    // operation, kept as the reference for the Montgomery and Barrett version. The scalar functions
+    // are checked against it directly and avx2.rs checks that the AVX2 versions match them.
+    mod reference {
+        use super::{Q, ZETA_2BITREV71, ZETA_BITREV7};
+
+        fn mul(a: u16, b: u16) -> u16 {
+            ((a as u32 * b as u32) % Q as u32) as u16
+        }
+
+        fn add(a: u16, b: u16) -> u16 {
+            (a + b) % Q as u16
+        }
+
+        fn sub(a: u16, b: u16) -> u16 {
+            (a + Q as u16 - b) % Q as u16
+        }
+
+        pub(super) fn ntt(f: &[u16; 256]) -> [u16; 256] {
+            let mut f_ntt = *f;
+            let mut k = 1;
+            let mut len = 128;
+            while len >= 2 {
+                for start in (0..256).step_by(2 * len) {
+                    let zeta = ZETA_BITREV7[k] as u16;
+                    k += 1;
+                    for j in start..start + len {
+                        let t = mul(zeta, f_ntt[j + len]);
+                        f_ntt[j + len] = sub(f_ntt[j], t);
+                        f_ntt[j] = add(f_ntt[j], t);
+                    }
+                }
+                len /= 2;
+            }
+            f_ntt
+        }
+
+        pub(super) fn ntt_inv(f_ntt: &[u16; 256]) -> [u16; 256] {
+            let mut f = *f_ntt;
+            let mut k = 127;
+            let mut len = 2;
+            while len <= 128 {
+                for start in (0..256).step_by(2 * len) {
+                    let zeta = ZETA_BITREV7[k] as u16;
+                    k -= 1;
+                    for j in start..start + len {
+                        let t = f[j];
+                        f[j] = add(t, f[j + len]);
+                        f[j + len] = mul(zeta, sub(f[j + len], t));
+                    }
+                }
+                len *= 2;
+            }
+            f.map(|x| mul(x, 3303))
+        }
+
+        #[allow(clippy::needless_range_loop)]
+        pub(super) fn multiply_ntts(f_ntt: &[u16; 256], g_ntt: &[u16; 256]) -> [u16; 256] {
+            let mut h_ntt = [0u16; 256];
+            for i in 0..128 {
+                let (left, right) = (2 * i, 2 * i + 1);
+                h_ntt[left] = add(mul(f_ntt[left], g_ntt[left]), mul(f_ntt[right], mul(g_ntt[right], ZETA_2BITREV71[i] as u16)));
+                h_ntt[right] = add(mul(f_ntt[left], g_ntt[right]), mul(f_ntt[right], g_ntt[left]));
+            }
+            h_ntt
+        }
+
+        pub(super) fn poly_vec_mult(a: &[[u16; 256]], b: &[[u16; 256]]) -> [u16; 256] {
+            let mut result = [0u16; 256];
+            for (a_i, b_i) in a.iter().zip(b.iter()) {
+                let product = multiply_ntts(a_i, b_i);
+                for j in 0..256 {
+                    result[j] = add(result[j], product[j]);
+                }
+            }
+            result
+        }
+    }
+
+    fn to_u16(f: &[i16; 256]) -> [u16; 256] {
+        f.map(|x| x as u16)
+    }
+
+    fn to_u16_vec(v: &[[i16; 256]]) -> Vec<[u16; 256]> {
+        v.iter().map(to_u16).collect()
+    }
+
+    // Random canonical polynomials followed by the all zero and all q - 1 extremes
+    fn test_polys(count: usize) -> impl Iterator<Item = [i16; 256]> {
+        let mut rng = ChaCha20Rng::seed_from_u64(203);
+        let random = (0..count).map(move |_| core::array::from_fn(|_| (rng.next_u32() % Q as u32) as i16));
+        random.chain([[0i16; 256], [Q - 1; 256]])
+    }
+
+    #[test]
+    fn test_ntt_matches_reference() {
+        for f in test_polys(200) {
+            let f_ntt = ntt_scalar(&f);
+            assert_eq!(to_u16(&f_ntt), reference::ntt(&to_u16(&f)));
+            assert_eq!(to_u16(&ntt_inv_scalar(&f)), reference::ntt_inv(&to_u16(&f)));
+            assert_eq!(ntt_inv_scalar(&f_ntt), f);
+        }
+    }
+
+    #[test]
+    fn test_multiply_ntts_matches_reference() {
+        let polys: Vec<_> = test_polys(100).collect();
+        for (f, g) in polys.iter().zip(polys.iter().rev()) {
+            let mut acc = [0i32; 256];
+            multiply_ntts_acc_scalar(f, g, &mut acc);
+            assert_eq!(to_u16(&reduce_acc_scalar(&acc)), reference::multiply_ntts(&to_u16(f), &to_u16(g)));
+        }
+    }
+
+    #[test]
+    #[allow(clippy::needless_range_loop)]
+    fn test_lazy_accumulation_matches_reference() {
+        // MAX_K products of the largest coefficients come closest to the bound of montgomery_reduce
+        let mut polys = test_polys(MAX_K * MAX_K + MAX_K);
+        for extreme in [false, true] {
+            let mut a = [[[0i16; 256]; MAX_K]; MAX_K];
+            let mut b = [[0i16; 256]; MAX_K];
+            for i in 0..MAX_K {
+                for j in 0..MAX_K {
+                    a[i][j] = if extreme { [Q - 1; 256] } else { polys.next().unwrap() };
+                }
+                b[i] = if extreme { [Q - 1; 256] } else { polys.next().unwrap() };
+            }
+
+            for k in 2..=MAX_K {
+                let b_u16 = to_u16_vec(&b[..k]);
+                let product = poly_mat_mult(&a, &b[..k]);
+                let transpose_product = poly_mat_transpose_mult(&a, &b[..k]);
+                for i in 0..k {
+                    let row = to_u16_vec(&a[i][..k]);
+                    let column: Vec<_> = (0..k).map(|j| to_u16(&a[j][i])).collect();
+                    assert_eq!(to_u16(&product[i]), reference::poly_vec_mult(&row, &b_u16));
+                    assert_eq!(to_u16(&transpose_product[i]), reference::poly_vec_mult(&column, &b_u16));
+                }
+                assert_eq!(to_u16(&poly_vec_mult(&a[0][..k], &b[..k])), reference::poly_vec_mult(&to_u16_vec(&a[0][..k]), &b_u16));
+            }
+        }
+    }
 
     #[test]
     fn test_ntt() {
@@ -182,4 +418,4 @@ mod tests {
         let mult_tq = multiply_ntts(&f_tq, &g_tq);
         assert_eq!(mult_tq, expected);
     }
-}
\ No newline at end of file
+}
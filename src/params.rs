@@ -0,0 +1,114 @@
+use zeroize::Zeroize;
+
+use crate::MlKemParams;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Fixed-size byte storage for keys and ciphertexts.
+pub trait ByteArray: AsRef<[u8]> + AsMut<[u8]> + Copy + Zeroize + sealed::Sealed {
+    const LEN: usize;
+
+    fn zeroed() -> Self;
+}
+
+impl<const N: usize> sealed::Sealed for [u8; N] {}
+
+impl<const N: usize> ByteArray for [u8; N] {
+    const LEN: usize = N;
+
+    fn zeroed() -> Self {
+        [0u8; N]
+    }
+}
+
+/// An ML-KEM parameter set from FIPS 203 Table 2, known at compile time.
+pub trait ParameterSet: sealed::Sealed + Copy + Default + 'static {
+    const PARAMS: MlKemParams;
+    const K: usize;
+    const ETA1: u8;
+    const ETA2: u8;
+    const DU: u8;
+    const DV: u8;
+
+    /// `[u8; 384k + 32]`
+    type EncapsKeyBytes: ByteArray;
+    /// `[u8; 768k + 96]`
+    type DecapsKeyBytes: ByteArray;
+    /// `[u8; 32(du * k + dv)]`
+    type CipherTextBytes: ByteArray;
+}
+
+/// ML-KEM-512 (security category 1).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MlKem512;
+
+/// ML-KEM-768 (security category 3).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MlKem768;
+
+/// ML-KEM-1024 (security category 5).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MlKem1024;
+
+impl sealed::Sealed for MlKem512 {}
+impl sealed::Sealed for MlKem768 {}
+impl sealed::Sealed for MlKem1024 {}
+
+impl ParameterSet for MlKem512 {
+    const PARAMS: MlKemParams = MlKemParams::MlKem512;
+    const K: usize = 2;
+    const ETA1: u8 = 3;
+    const ETA2: u8 = 2;
+    const DU: u8 = 10;
+    const DV: u8 = 4;
+
+    type EncapsKeyBytes = [u8; 800];
+    type DecapsKeyBytes = [u8; 1632];
+    type CipherTextBytes = [u8; 768];
+}
+
+impl ParameterSet for MlKem768 {
+    const PARAMS: MlKemParams = MlKemParams::MlKem768;
+    const K: usize = 3;
+    const ETA1: u8 = 2;
+    const ETA2: u8 = 2;
+    const DU: u8 = 10;
+    const DV: u8 = 4;
+
+    type EncapsKeyBytes = [u8; 1184];
+    type DecapsKeyBytes = [u8; 2400];
+    type CipherTextBytes = [u8; 1088];
+}
+
+impl ParameterSet for MlKem1024 {
+    const PARAMS: MlKemParams = MlKemParams::MlKem1024;
+    const K: usize = 4;
+    const ETA1: u8 = 2;
+    const ETA2: u8 = 2;
+    const DU: u8 = 11;
+    const DV: u8 = 5;
+
+    type EncapsKeyBytes = [u8; 1568];
+    type DecapsKeyBytes = [u8; 3168];
+    type CipherTextBytes = [u8; 1568];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_sizes<P: ParameterSet>() {
+        assert_eq!(P::EncapsKeyBytes::LEN, 384 * P::K + 32);
+        assert_eq!(P::DecapsKeyBytes::LEN, 768 * P::K + 96);
+        assert_eq!(P::CipherTextBytes::LEN, 32 * (P::DU as usize * P::K + P::DV as usize));
+    }
+
+    #[test]
+    fn test_parameter_set_sizes() {
+        check_sizes::<MlKem512>();
+        check_sizes::<MlKem768>();
+        check_sizes::<MlKem1024>();
+    }
+}
@@ -0,0 +1,567 @@
+//! DER and PEM encodings of ML-KEM keys using the NIST `id-alg-ml-kem-*` object identifiers.
+//!
+//! Encapsulation keys are SubjectPublicKeyInfo (RFC 5280) and decapsulation keys are PKCS#8
+//! OneAsymmetricKey (RFC 5958). The private key is the ML-KEM-PrivateKey CHOICE from the IETF
+//! LAMPS ML-KEM certificate profile, holding the 64-byte seed `d || z`, the expanded key, or both.
+
+use alloc::{string::String, vec, vec::Vec};
+
+use pem_rfc7468::LineEnding;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use crate::{Error, MlKemParams, ml_kem_internal::{check_decaps_key, check_encaps_key, ml_kem_keygen_internal},
+params::{ByteArray, ParameterSet}, typed, types::{DecapsKey, DecapsKeySeed, EncapsKey}};
+
+/// The choice of `ML-KEM-PrivateKey` written into a PKCS#8 document.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrivateKeyFormat {
+    /// `seed [0] OCTET STRING (SIZE (64))`
+    Seed,
+    /// `expandedKey OCTET STRING`
+    ExpandedKey,
+    /// `both SEQUENCE { seed, expandedKey }`
+    Both,
+}
+
+const PUBLIC_KEY_LABEL: &str = "PUBLIC KEY";
+const PRIVATE_KEY_LABEL: &str = "PRIVATE KEY";
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SEED: u8 = 0x80;
+const TAG_PUBLIC_KEY: u8 = 0x81;
+const TAG_ATTRIBUTES: u8 = 0xA0;
+
+// 2.16.840.1.101.3.4.4 without the final arc
+const OID_PREFIX: [u8; 8] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x04];
+
+// SEQUENCE { OBJECT IDENTIFIER } with the parameters absent
+const ALGORITHM_ID_LEN: usize = 13;
+
+// eta1 from FIPS 203 Table 2 and the final arc of the object identifier
+fn params_info(params: MlKemParams) -> (u8, u8) {
+    match params {
+        MlKemParams::MlKem512 => (3, 1),
+        MlKemParams::MlKem768 => (2, 2),
+        MlKemParams::MlKem1024 => (2, 3),
+    }
+}
+
+fn params_from_arc(arc: u8) -> Option<MlKemParams> {
+    match arc {
+        1 => Some(MlKemParams::MlKem512),
+        2 => Some(MlKemParams::MlKem768),
+        3 => Some(MlKemParams::MlKem1024),
+        _ => None,
+    }
+}
+
+// Length of a DER element with the given content length, the keys never need more than two length octets
+fn tlv_len(len: usize) -> usize {
+    match len {
+        0..=0x7F => 2 + len,
+        0x80..=0xFF => 3 + len,
+        _ => 4 + len,
+    }
+}
+
+fn write_header(out: &mut Vec<u8>, tag: u8, len: usize) {
+    out.push(tag);
+    match len {
+        0..=0x7F => out.push(len as u8),
+        0x80..=0xFF => out.extend_from_slice(&[0x81, len as u8]),
+        _ => out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+    }
+}
+
+fn write_algorithm_id(out: &mut Vec<u8>, params: MlKemParams) {
+    write_header(out, TAG_SEQUENCE, ALGORITHM_ID_LEN - 2);
+    write_header(out, TAG_OID, OID_PREFIX.len() + 1);
+    out.extend_from_slice(&OID_PREFIX);
+    out.push(params_info(params).1);
+}
+
+// Reads DER elements with definite lengths in their minimal encoding
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn peek_tag(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    fn read(&mut self, tag: u8) -> Result<&'a [u8], Error> {
+        let (&actual, rest) = self.0.split_first().ok_or(Error::InvalidEncoding)?;
+        let (&first, rest) = rest.split_first().ok_or(Error::InvalidEncoding)?;
+        if actual != tag {
+            return Err(Error::InvalidEncoding);
+        }
+        let (len, rest) = match first {
+            0..=0x7F => (first as usize, rest),
+            0x81 => match rest {
+                [len @ 0x80..=0xFF, rest @ ..] => (*len as usize, rest),
+                _ => return Err(Error::InvalidEncoding),
+            },
+            0x82 => match rest {
+                [hi @ 0x01..=0xFF, lo, rest @ ..] => (((*hi as usize) << 8) | *lo as usize, rest),
+                _ => return Err(Error::InvalidEncoding),
+            },
+            _ => return Err(Error::InvalidEncoding),
+        };
+        if rest.len() < len {
+            return Err(Error::InvalidEncoding);
+        }
+        let (value, rest) = rest.split_at(len);
+        self.0 = rest;
+        Ok(value)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.0.is_empty() { Ok(()) } else { Err(Error::InvalidEncoding) }
+    }
+}
+
+fn read_algorithm_id(reader: &mut Reader<'_>) -> Result<MlKemParams, Error> {
+    let mut algorithm = Reader(reader.read(TAG_SEQUENCE)?);
+    let oid = algorithm.read(TAG_OID)?;
+    algorithm.finish()?;
+    match oid {
+        [prefix @ .., arc] if prefix == OID_PREFIX => params_from_arc(*arc).ok_or(Error::InvalidEncoding),
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+// BIT STRING contents with no unused bits
+fn bit_string_bytes(bits: &[u8]) -> Result<&[u8], Error> {
+    match bits {
+        [0, bytes @ ..] => Ok(bytes),
+        _ => Err(Error::InvalidEncoding),
+    }
+}
+
+fn encode_public_key(params: MlKemParams, ek: &[u8]) -> Vec<u8> {
+    let key_len = tlv_len(ek.len() + 1);
+    let mut out = Vec::with_capacity(tlv_len(ALGORITHM_ID_LEN + key_len));
+    write_header(&mut out, TAG_SEQUENCE, ALGORITHM_ID_LEN + key_len);
+    write_algorithm_id(&mut out, params);
+    write_header(&mut out, TAG_BIT_STRING, ek.len() + 1);
+    out.push(0);
+    out.extend_from_slice(ek);
+    out
+}
+
+// Returns the encapsulation key after the FIPS 203 §7.2 checks
+fn decode_public_key(der: &[u8]) -> Result<(MlKemParams, &[u8]), Error> {
+    let mut outer = Reader(der);
+    let mut spki = Reader(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+    let params = read_algorithm_id(&mut spki)?;
+    let ek = bit_string_bytes(spki.read(TAG_BIT_STRING)?)?;
+    spki.finish()?;
+
+    if !check_encaps_key(params.k(), ek) {
+        return Err(Error::InvalidEncapsKey);
+    }
+    Ok((params, ek))
+}
+
+// The expanded key is derived from the seed for every format but Seed
+fn encode_private_key(params: MlKemParams, seed: Option<&DecapsKeySeed>, dk: Option<&[u8]>) -> Zeroizing<Vec<u8>> {
+    let seed_len = 2 + 64;
+    let private_key_len = match (seed, dk) {
+        (Some(_), None) => seed_len,
+        (None, Some(dk)) => tlv_len(dk.len()),
+        (Some(_), Some(dk)) => tlv_len(seed_len + tlv_len(dk.len())),
+        (None, None) => unreachable!("a private key needs a seed or an expanded key"),
+    };
+    let key_info_len = 3 + ALGORITHM_ID_LEN + tlv_len(private_key_len);
+
+    let mut out = Zeroizing::new(Vec::with_capacity(tlv_len(key_info_len)));
+    write_header(&mut out, TAG_SEQUENCE, key_info_len);
+    // version v1(0)
+    write_header(&mut out, TAG_INTEGER, 1);
+    out.push(0);
+    write_algorithm_id(&mut out, params);
+    write_header(&mut out, TAG_OCTET_STRING, private_key_len);
+    match (seed, dk) {
+        (Some(seed), None) => {
+            write_header(&mut out, TAG_SEED, 64);
+            out.extend_from_slice(&seed.0);
+        }
+        (None, Some(dk)) => {
+            write_header(&mut out, TAG_OCTET_STRING, dk.len());
+            out.extend_from_slice(dk);
+        }
+        (Some(seed), Some(dk)) => {
+            write_header(&mut out, TAG_SEQUENCE, seed_len + tlv_len(dk.len()));
+            write_header(&mut out, TAG_OCTET_STRING, 64);
+            out.extend_from_slice(&seed.0);
+            write_header(&mut out, TAG_OCTET_STRING, dk.len());
+            out.extend_from_slice(dk);
+        }
+        (None, None) => unreachable!(),
+    }
+    out
+}
+
+fn expand_seed(params: MlKemParams, seed: &DecapsKeySeed) -> Zeroizing<Vec<u8>> {
+    let (eta1, _) = params_info(params);
+    let mut ek = vec![0u8; params.encaps_key_len()];
+    let mut dk = Zeroizing::new(vec![0u8; params.decaps_key_len()]);
+    ml_kem_keygen_internal(params.k(), eta1, seed.d(), seed.z(), &mut ek, &mut dk);
+    dk
+}
+
+struct PrivateKey {
+    params: MlKemParams,
+    seed: Option<DecapsKeySeed>,
+    dk: Zeroizing<Vec<u8>>,
+}
+
+// The expanded decapsulation key is checked against the seed when both are present
+// and otherwise against FIPS 203 §7.3
+fn decode_private_key(der: &[u8]) -> Result<PrivateKey, Error> {
+    let mut outer = Reader(der);
+    let mut key_info = Reader(outer.read(TAG_SEQUENCE)?);
+    outer.finish()?;
+    // v1(0), or v2(1) which allows the public key
+    let version = match key_info.read(TAG_INTEGER)? {
+        [version @ (0 | 1)] => *version,
+        _ => return Err(Error::InvalidEncoding),
+    };
+    let params = read_algorithm_id(&mut key_info)?;
+    let mut private_key = Reader(key_info.read(TAG_OCTET_STRING)?);
+    if key_info.peek_tag() == Some(TAG_ATTRIBUTES) {
+        key_info.read(TAG_ATTRIBUTES)?;
+    }
+    let public_key = match key_info.peek_tag() {
+        Some(TAG_PUBLIC_KEY) if version == 1 => Some(bit_string_bytes(key_info.read(TAG_PUBLIC_KEY)?)?),
+        _ => None,
+    };
+    key_info.finish()?;
+
+    let k = params.k();
+    let read_seed = |bytes: &[u8]| -> Result<DecapsKeySeed, Error> {
+        let mut seed = DecapsKeySeed([0u8; 64]);
+        if bytes.len() != 64 {
+            return Err(Error::InvalidEncoding);
+        }
+        seed.0.copy_from_slice(bytes);
+        Ok(seed)
+    };
+    let (seed, dk) = match private_key.peek_tag() {
+        Some(TAG_SEED) => {
+            let seed = read_seed(private_key.read(TAG_SEED)?)?;
+            let dk = expand_seed(params, &seed);
+            (Some(seed), dk)
+        }
+        Some(TAG_OCTET_STRING) => {
+            let dk = private_key.read(TAG_OCTET_STRING)?;
+            if !check_decaps_key(k, dk) {
+                return Err(Error::InvalidDecapsKey);
+            }
+            (None, Zeroizing::new(dk.to_vec()))
+        }
+        _ => {
+            let mut both = Reader(private_key.read(TAG_SEQUENCE)?);
+            let seed = read_seed(both.read(TAG_OCTET_STRING)?)?;
+            let expanded = both.read(TAG_OCTET_STRING)?;
+            both.finish()?;
+            let dk = expand_seed(params, &seed);
+            if !bool::from(dk.as_slice().ct_eq(expanded)) {
+                return Err(Error::InvalidDecapsKey);
+            }
+            (Some(seed), dk)
+        }
+    };
+    private_key.finish()?;
+
+    if public_key.is_some_and(|ek| ek != &dk[384 * k .. 768 * k + 32]) {
+        return Err(Error::InvalidDecapsKey);
+    }
+    Ok(PrivateKey { params, seed, dk })
+}
+
+fn encode_pem(label: &str, der: &[u8]) -> String {
+    pem_rfc7468::encode_string(label, LineEnding::LF, der).expect("DER documents fit in a PEM string")
+}
+
+fn decode_pem(label: &str, pem: &str) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let (actual, der) = pem_rfc7468::decode_vec(pem.as_bytes()).map_err(|_| Error::InvalidEncoding)?;
+    let der = Zeroizing::new(der);
+    if actual != label {
+        return Err(Error::InvalidEncoding);
+    }
+    Ok(der)
+}
+
+impl EncapsKey {
+    /// Encodes the key as SubjectPublicKeyInfo DER, the parameter set is given by the key length.
+    pub fn to_public_key_der(&self) -> Result<Vec<u8>, Error> {
+        let params = MlKemParams::from_encaps_key_len(self.0.len()).ok_or(Error::InvalidEncapsKey)?;
+        Ok(encode_public_key(params, &self.0))
+    }
+
+    pub fn to_public_key_pem(&self) -> Result<String, Error> {
+        Ok(encode_pem(PUBLIC_KEY_LABEL, &self.to_public_key_der()?))
+    }
+
+    pub fn from_public_key_der(der: &[u8]) -> Result<(MlKemParams, Self), Error> {
+        let (params, ek) = decode_public_key(der)?;
+        Ok((params, EncapsKey(ek.to_vec())))
+    }
+
+    pub fn from_public_key_pem(pem: &str) -> Result<(MlKemParams, Self), Error> {
+        Self::from_public_key_der(&decode_pem(PUBLIC_KEY_LABEL, pem)?)
+    }
+}
+
+impl DecapsKey {
+    /// Encodes the expanded key as PKCS#8 DER, the parameter set is given by the key length.
+    ///
+    /// Use `DecapsKeySeed::to_pkcs8_der` to include the seed.
+    pub fn to_pkcs8_der(&self) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let params = MlKemParams::from_decaps_key_len(self.0.len()).ok_or(Error::InvalidDecapsKey)?;
+        Ok(encode_private_key(params, None, Some(&self.0)))
+    }
+
+    pub fn to_pkcs8_pem(&self) -> Result<Zeroizing<String>, Error> {
+        Ok(Zeroizing::new(encode_pem(PRIVATE_KEY_LABEL, &self.to_pkcs8_der()?)))
+    }
+
+    /// Decodes a PKCS#8 document holding the seed, the expanded key or both.
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<(MlKemParams, Self), Error> {
+        let mut key = decode_private_key(der)?;
+        Ok((key.params, DecapsKey(core::mem::take(&mut *key.dk))))
+    }
+
+    pub fn from_pkcs8_pem(pem: &str) -> Result<(MlKemParams, Self), Error> {
+        Self::from_pkcs8_der(&decode_pem(PRIVATE_KEY_LABEL, pem)?)
+    }
+}
+
+impl DecapsKeySeed {
+    /// Encodes the seed as PKCS#8 DER for the parameter set, expanding the key if the format includes it.
+    pub fn to_pkcs8_der(&self, params: MlKemParams, format: PrivateKeyFormat) -> Zeroizing<Vec<u8>> {
+        match format {
+            PrivateKeyFormat::Seed => encode_private_key(params, Some(self), None),
+            PrivateKeyFormat::ExpandedKey => encode_private_key(params, None, Some(&expand_seed(params, self))),
+            PrivateKeyFormat::Both => encode_private_key(params, Some(self), Some(&expand_seed(params, self))),
+        }
+    }
+
+    pub fn to_pkcs8_pem(&self, params: MlKemParams, format: PrivateKeyFormat) -> Zeroizing<String> {
+        Zeroizing::new(encode_pem(PRIVATE_KEY_LABEL, &self.to_pkcs8_der(params, format)))
+    }
+
+    /// Decodes a PKCS#8 document holding the seed or both, documents with only the expanded key are rejected.
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<(MlKemParams, Self), Error> {
+        let key = decode_private_key(der)?;
+        match key.seed {
+            Some(seed) => Ok((key.params, seed)),
+            None => Err(Error::InvalidEncoding),
+        }
+    }
+
+    pub fn from_pkcs8_pem(pem: &str) -> Result<(MlKemParams, Self), Error> {
+        Self::from_pkcs8_der(&decode_pem(PRIVATE_KEY_LABEL, pem)?)
+    }
+}
+
+impl<P: ParameterSet> typed::EncapsKey<P> {
+    pub fn to_public_key_der(&self) -> Vec<u8> {
+        encode_public_key(P::PARAMS, self.0.as_ref())
+    }
+
+    pub fn to_public_key_pem(&self) -> String {
+        encode_pem(PUBLIC_KEY_LABEL, &self.to_public_key_der())
+    }
+
+    /// Decodes SubjectPublicKeyInfo DER, which must name the parameter set `P`.
+    pub fn from_public_key_der(der: &[u8]) -> Result<Self, Error> {
+        match decode_public_key(der)? {
+            (params, ek) if params == P::PARAMS => Self::from_slice(ek),
+            _ => Err(Error::InvalidEncoding),
+        }
+    }
+
+    pub fn from_public_key_pem(pem: &str) -> Result<Self, Error> {
+        Self::from_public_key_der(&decode_pem(PUBLIC_KEY_LABEL, pem)?)
+    }
+}
+
+impl<P: ParameterSet> typed::DecapsKey<P> {
+    /// Encodes the expanded key as PKCS#8 DER.
+    pub fn to_pkcs8_der(&self) -> Zeroizing<Vec<u8>> {
+        encode_private_key(P::PARAMS, None, Some(self.0.as_ref()))
+    }
+
+    pub fn to_pkcs8_pem(&self) -> Zeroizing<String> {
+        Zeroizing::new(encode_pem(PRIVATE_KEY_LABEL, &self.to_pkcs8_der()))
+    }
+
+    /// Decodes a PKCS#8 document holding the seed, the expanded key or both, which must name the parameter set `P`.
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<Self, Error> {
+        let key = decode_private_key(der)?;
+        if key.params != P::PARAMS {
+            return Err(Error::InvalidEncoding);
+        }
+        let mut dk = typed::DecapsKey::<P>(P::DecapsKeyBytes::zeroed());
+        dk.0.as_mut().copy_from_slice(&key.dk);
+        Ok(dk)
+    }
+
+    pub fn from_pkcs8_pem(pem: &str) -> Result<Self, Error> {
+        Self::from_pkcs8_der(&decode_pem(PRIVATE_KEY_LABEL, pem)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MlKem512, MlKem768, MlKem1024, typed::MlKem};
+
+    const PUB_PEM: &str = include_str!("../tests/data/ml_kem_512_pub.pem");
+    const SEED_PEM: &str = include_str!("../tests/data/ml_kem_512_seed.pem");
+    const EXPANDED_PEM: &str = include_str!("../tests/data/ml_kem_512_expanded.pem");
+    const BOTH_PEM: &str = include_str!("../tests/data/ml_kem_512_both.pem");
+
+    // The OpenSSL 3.5 test keys were generated from the seed 00 01 .. 3f
+    fn openssl_seed() -> DecapsKeySeed {
+        DecapsKeySeed(core::array::from_fn(|i| i as u8))
+    }
+
+    #[test]
+    fn test_openssl_interop() {
+        let (ek, dk) = MlKem::<MlKem512>::new().keygen_from_seed(&openssl_seed());
+
+        assert_eq!(ek.to_public_key_pem(), PUB_PEM);
+        assert_eq!(*openssl_seed().to_pkcs8_pem(MlKemParams::MlKem512, PrivateKeyFormat::Seed), SEED_PEM);
+        assert_eq!(*openssl_seed().to_pkcs8_pem(MlKemParams::MlKem512, PrivateKeyFormat::ExpandedKey), EXPANDED_PEM);
+        assert_eq!(*openssl_seed().to_pkcs8_pem(MlKemParams::MlKem512, PrivateKeyFormat::Both), BOTH_PEM);
+        assert_eq!(*dk.to_pkcs8_pem(), EXPANDED_PEM);
+
+        assert_eq!(typed::EncapsKey::<MlKem512>::from_public_key_pem(PUB_PEM).unwrap().as_bytes(), ek.as_bytes());
+        for pem in [SEED_PEM, EXPANDED_PEM, BOTH_PEM] {
+            assert_eq!(typed::DecapsKey::<MlKem512>::from_pkcs8_pem(pem).unwrap().as_bytes(), dk.as_bytes());
+        }
+        assert_eq!(DecapsKeySeed::from_pkcs8_pem(BOTH_PEM).unwrap().1.into_bytes(), openssl_seed().into_bytes());
+        assert_eq!(DecapsKeySeed::from_pkcs8_pem(EXPANDED_PEM).err(), Some(Error::InvalidEncoding));
+    }
+
+    fn check_roundtrip<P: ParameterSet>() {
+        let seed = DecapsKeySeed([7u8; 64]);
+        let (ek, dk) = MlKem::<P>::new().keygen_from_seed(&seed);
+
+        let ek_der = ek.to_public_key_der();
+        assert_eq!(typed::EncapsKey::<P>::from_public_key_der(&ek_der).unwrap().as_bytes().as_ref(), ek.as_bytes().as_ref());
+        let (params, ek_untyped) = EncapsKey::from_public_key_der(&ek_der).unwrap();
+        assert_eq!(params, P::PARAMS);
+        assert_eq!(ek_untyped.to_public_key_der().unwrap(), ek_der);
+
+        for format in [PrivateKeyFormat::Seed, PrivateKeyFormat::ExpandedKey, PrivateKeyFormat::Both] {
+            let dk_der = seed.to_pkcs8_der(P::PARAMS, format);
+            assert_eq!(typed::DecapsKey::<P>::from_pkcs8_der(&dk_der).unwrap().as_bytes().as_ref(), dk.as_bytes().as_ref());
+            let (params, dk_untyped) = DecapsKey::from_pkcs8_der(&dk_der).unwrap();
+            assert_eq!(params, P::PARAMS);
+            assert_eq!(dk_untyped.0, dk.as_bytes().as_ref());
+        }
+        assert_eq!(dk.to_pkcs8_der(), seed.to_pkcs8_der(P::PARAMS, PrivateKeyFormat::ExpandedKey));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        check_roundtrip::<MlKem512>();
+        check_roundtrip::<MlKem768>();
+        check_roundtrip::<MlKem1024>();
+    }
+
+    #[test]
+    fn test_public_key_der_layout() {
+        let (ek, _) = MlKem::<MlKem768>::new().keygen_from_seed(&DecapsKeySeed([1u8; 64]));
+        let der = ek.to_public_key_der();
+        assert_eq!(der[..22], [0x30, 0x82, 0x04, 0xB2, 0x30, 0x0B, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x04, 0x02,
+            0x03, 0x82, 0x04, 0xA1, 0x00]);
+        assert_eq!(der[22..], ek.as_bytes()[..]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_public_keys() {
+        let der = pem_der(PUB_PEM);
+        // Wrong parameter set, truncated, trailing data and an unsupported length encoding
+        assert_eq!(typed::EncapsKey::<MlKem768>::from_public_key_der(&der).err(), Some(Error::InvalidEncoding));
+        assert_eq!(EncapsKey::from_public_key_der(&der[..der.len() - 1]).err(), Some(Error::InvalidEncoding));
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert_eq!(EncapsKey::from_public_key_der(&trailing).err(), Some(Error::InvalidEncoding));
+        let mut non_minimal = der.clone();
+        non_minimal[1] = 0x83;
+        assert_eq!(EncapsKey::from_public_key_der(&non_minimal).err(), Some(Error::InvalidEncoding));
+        // Unknown algorithm arc
+        let mut unknown = der.clone();
+        unknown[16] = 4;
+        assert_eq!(EncapsKey::from_public_key_der(&unknown).err(), Some(Error::InvalidEncoding));
+        // The first coefficient is set to q, failing the modulus check
+        let mut modulus = der.clone();
+        modulus[22] = 0x01;
+        modulus[23] = (modulus[23] & 0xF0) | 0x0D;
+        assert_eq!(EncapsKey::from_public_key_der(&modulus).err(), Some(Error::InvalidEncapsKey));
+        // A private key label is not a public key
+        assert_eq!(EncapsKey::from_public_key_pem(SEED_PEM).err(), Some(Error::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_private_keys() {
+        let both = pem_der(BOTH_PEM);
+        // Changing the last byte of the expanded key (z) breaks the seed consistency check
+        let mut mismatched = both.clone();
+        *mismatched.last_mut().unwrap() ^= 1;
+        assert_eq!(DecapsKey::from_pkcs8_der(&mismatched).err(), Some(Error::InvalidDecapsKey));
+
+        // Changing H(ek) in the expanded key breaks the hash check
+        let mut expanded = pem_der(EXPANDED_PEM);
+        let len = expanded.len();
+        expanded[len - 64] ^= 1;
+        assert_eq!(DecapsKey::from_pkcs8_der(&expanded).err(), Some(Error::InvalidDecapsKey));
+
+        // A seed must be 64 bytes
+        let mut short_seed = pem_der(SEED_PEM);
+        short_seed[1] -= 1;
+        short_seed[19] -= 1;
+        short_seed[21] -= 1;
+        short_seed.pop();
+        assert_eq!(DecapsKey::from_pkcs8_der(&short_seed).err(), Some(Error::InvalidEncoding));
+
+        assert_eq!(typed::DecapsKey::<MlKem1024>::from_pkcs8_der(&both).err(), Some(Error::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_v2_public_key() {
+        let (ek, dk) = MlKem::<MlKem512>::new().keygen_from_seed(&openssl_seed());
+        let seed_der = pem_der(SEED_PEM);
+
+        // OneAsymmetricKey v2 with the public key [1] appended
+        let build = |ek: &[u8]| {
+            let body_len = seed_der.len() - 2 + tlv_len(ek.len() + 1);
+            let mut der = Vec::new();
+            write_header(&mut der, TAG_SEQUENCE, body_len);
+            der.extend_from_slice(&seed_der[2..]);
+            der[6] = 1;
+            write_header(&mut der, TAG_PUBLIC_KEY, ek.len() + 1);
+            der.push(0);
+            der.extend_from_slice(ek);
+            der
+        };
+        assert_eq!(typed::DecapsKey::<MlKem512>::from_pkcs8_der(&build(ek.as_bytes())).unwrap().as_bytes(), dk.as_bytes());
+        let mut other_ek = ek.into_bytes();
+        other_ek[799] ^= 1;
+        assert_eq!(typed::DecapsKey::<MlKem512>::from_pkcs8_der(&build(&other_ek)).err(), Some(Error::InvalidDecapsKey));
+    }
+
+    fn pem_der(pem: &str) -> Vec<u8> {
+        pem_rfc7468::decode_vec(pem.as_bytes()).unwrap().1
+    }
+}
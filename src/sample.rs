@@ -1,8 +1,10 @@
-use crate::Q;
+use crate::{Q, keccak::{SHAKE128_RATE, Shake128X4}};
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use crate::avx2;
 use sha3::{Shake128, digest::{ExtendableOutput, Update, XofReader}};
 
 // j and i are the extended bytes of the seed
-pub(crate) fn sample_ntt(seed: &[u8], j: u8, i: u8) -> [u16; 256] {
+pub(crate) fn sample_ntt(seed: &[u8], j: u8, i: u8) -> [i16; 256] {
     debug_assert!(seed.len() == 32, "Seed must be 32 bytes");
 
     let mut hasher = Shake128::default();  // ctx ← XOF.Init()
@@ -11,44 +13,92 @@ pub(crate) fn sample_ntt(seed: &[u8], j: u8, i: u8) -> [u16; 256] {
     hasher.update(&[i]);
     let mut reader = hasher.finalize_xof();
 
-    let mut output = [0u16; 256];
+    let mut output = [0i16; 256];
     let mut j = 0;
 
+    // Squeezing whole blocks reads the same stream as the 3 bytes at a time of the specification
+    let mut block = [0u8; SHAKE128_RATE];
     while j < 256 {
-        let mut buf = [0u8; 3];
-        reader.read(&mut buf);    // (ctx, C) ← XOF.Squeeze(ctx, 3)
+        reader.read(&mut block);    // (ctx, C) ← XOF.Squeeze(ctx, 3), 56 times
+        sample_block(&block, &mut output, &mut j);
+    }
+
+    output
+}
 
-        let d1 = buf[0] as u16 + 256 * (buf[1] as u16 & 0x0F); // d1 ← C[0] + 256 * (C[1] mod 16)
-        let d2 = (buf[1] as u16 >> 4) + 16 * buf[2] as u16;    // d2 ← floor(C[1] / 16) + 16 * C[2]
+// Four sample_ntt calls on the same seed, squeezing their SHAKE128 streams together
+pub(crate) fn sample_ntt_x4(seed: &[u8; 32], indices: [(u8, u8); 4]) -> [[i16; 256]; 4] {
+    let inputs = indices.map(|(j, i)| {
+        let mut input = [0u8; 34];
+        input[..32].copy_from_slice(seed);
+        input[32] = j;
+        input[33] = i;
+        input
+    });
+    let mut xof = Shake128X4::new(inputs.each_ref().map(|input| input.as_slice()));
+
+    let mut blocks = [[0u8; SHAKE128_RATE]; 4];
+    let mut output = [[0i16; 256]; 4];
+    let mut counts = [0usize; 4];
+
+    while counts.iter().any(|&j| j < 256) {
+        xof.squeeze_block(&mut blocks);
+        for ((block, a), j) in blocks.iter().zip(&mut output).zip(&mut counts) {
+            sample_block(block, a, j);
+        }
+    }
+
+    output
+}
+
+// Rejection samples one block of XOF output into a from a[j] on, stopping once a is full. The rate is
+// a multiple of 3, so every block holds whole candidate pairs.
+fn sample_block(block: &[u8; SHAKE128_RATE], a: &mut [i16; 256], j: &mut usize) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if avx2::enabled() {
+        // SAFETY: the CPU supports AVX2, the only target feature the function enables
+        let parsed = unsafe { avx2::sample_block(block, a, j) };
+        return sample_bytes_scalar(&block[parsed..], a, j);
+    }
+    sample_bytes_scalar(block, a, j)
+}
+
+pub(crate) fn sample_bytes_scalar(bytes: &[u8], a: &mut [i16; 256], j: &mut usize) {
+    for buf in bytes.as_chunks::<3>().0 {
+        if *j == 256 {
+            break;
+        }
+
+        let d1 = buf[0] as i16 + 256 * (buf[1] as i16 & 0x0F); // d1 ← C[0] + 256 * (C[1] mod 16)
+        let d2 = (buf[1] as i16 >> 4) + 16 * buf[2] as i16;    // d2 ← floor(C[1] / 16) + 16 * C[2]
 
         if d1 < Q {
-            output[j] = d1;    // a[j] ← d1
-            j += 1;
+            a[*j] = d1;    // a[j] ← d1
+            *j += 1;
         }
 
-        if d2 < Q && j < 256 {
-            output[j] = d2;    // a[j] ← d2
-            j += 1;
+        if d2 < Q && *j < 256 {
+            a[*j] = d2;    // a[j] ← d2
+            *j += 1;
         }
     }
-
-    output
 }
 
 // Remainders are precomputed outside of the sample_poly_cbd function
-const ETA_SUB_MODS: [[u16; 4]; 4] = [
+const ETA_SUB_MODS: [[i16; 4]; 4] = [
     [    0, Q - 1, Q - 2, Q - 3],
     [    1,     0, Q - 1, Q - 2],
     [    2,     1,     0, Q - 1],
     [    3,     2,     1,     0],
 ];
 
-pub(crate) fn sample_poly_cbd(seed: &[u8]) -> [u16; 256] {
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn sample_poly_cbd(seed: &[u8]) -> [i16; 256] {
     let eta = seed.len() / 64;
     debug_assert!((eta == 2 || eta == 3), "eta must be 2 or 3");
     debug_assert_eq!(seed.len(), 64 * eta, "Seed must be 64 * eta bytes");
 
-    let mut poly = [0u16; 256];
+    let mut poly = [0i16; 256];
     let mut byte_index = 0;
     let mut work_chunk: u32 = 0;
     let mut bits_loaded = 0;
@@ -76,6 +126,8 @@ pub(crate) fn sample_poly_cbd(seed: &[u8]) -> [u16; 256] {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{RngCore, SeedableRng};
 
     #[test]
     fn test_sample_ntt() {
@@ -87,6 +139,20 @@ mod tests {
         assert_eq!(expected, poly);
     }
 
+    #[test]
+    fn test_sample_ntt_x4_matches_sample_ntt() {
+        let mut rng = ChaCha20Rng::seed_from_u64(20);
+        for _ in 0..50 {
+            let mut seed = [0u8; 32];
+            rng.fill_bytes(&mut seed);
+            let indices = core::array::from_fn(|_| (rng.next_u32() as u8, rng.next_u32() as u8));
+            let polys = sample_ntt_x4(&seed, indices);
+            for (poly, (j, i)) in polys.iter().zip(indices) {
+                assert_eq!(*poly, sample_ntt(&seed, j, i));
+            }
+        }
+    }
+
     #[test]
     fn test_sample_poly_cbd() {
         let seed = [20, 219, 21, 225, 180, 60, 28, 210, 218, 147, 188, 76, 197, 139, 52, 82, 16, 43, 99, 22, 108, 106, 188, 155, 104, 17, 209, 184, 204, 34, 60, 162, 137, 87, 163, 35, 152, 70, 246, 66, 108, 209, 142, 162, 32, 8, 158, 181, 222, 148, 79, 123, 119, 81, 216, 204, 72, 211, 109, 148, 87, 174, 83, 21, 142, 77, 164, 135, 199, 181, 251, 238, 129, 161, 202, 154, 216, 162, 8, 55, 106, 82, 42, 242, 92, 193, 253, 37, 31, 12, 204, 23, 154, 237, 86, 55, 220, 44, 4, 133, 22, 222, 173, 12, 65, 10, 230, 28, 44, 93, 237, 149, 78, 137, 45, 5, 127, 115, 96, 47, 153, 208, 137, 248, 106, 236, 222, 18, 198, 102, 136, 152, 155, 33, 55, 228, 90, 159, 49, 223, 101, 202, 228, 168, 19, 211, 191, 247, 152, 19, 58, 63, 96, 221, 30, 121, 52, 39, 123, 103, 32, 168, 73, 143, 70, 126, 229, 66, 25, 254, 83, 77, 14, 82, 65, 106, 240, 10, 243, 67, 121, 219, 216, 12, 184, 68, 248, 196, 241, 8, 80, 32];
@@ -0,0 +1,388 @@
+use core::fmt;
+
+use base64ct::{Base64, Encoding};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::{self, Unexpected, Visitor}};
+use zeroize::Zeroize;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{ml_kem_internal::check_decaps_key, params::{ByteArray, ParameterSet}, typed, types::DecapsKeySeed};
+#[cfg(feature = "alloc")]
+use crate::{ml_kem_internal::check_encaps_key, params::{MlKem512, MlKem768, MlKem1024}, types::{CipherText, DecapsKey, EncapsKey}};
+
+// Human-readable formats get padded base64 strings, binary formats get raw bytes
+
+// Streams the base64 encoding to the formatter so no buffer for the whole string is needed
+struct Base64Display<'a>(&'a [u8]);
+
+impl fmt::Display for Base64Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 48 input bytes are exactly 64 characters, so only the last chunk is padded
+        let mut buf = [0u8; 64];
+        let mut result = Ok(());
+        for chunk in self.0.chunks(48) {
+            result = match Base64::encode(chunk, &mut buf) {
+                Ok(encoded) => f.write_str(encoded),
+                Err(_) => Err(fmt::Error),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        buf.zeroize();
+        result
+    }
+}
+
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.collect_str(&Base64Display(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+// Storage that a byte string is decoded into, chosen once the decoded length is known
+trait ByteSink {
+    fn buffer(&mut self, len: usize) -> Option<&mut [u8]>;
+}
+
+// Writes into an existing buffer of exactly the expected length
+struct FixedSink<'a>(&'a mut [u8]);
+
+impl ByteSink for FixedSink<'_> {
+    fn buffer(&mut self, len: usize) -> Option<&mut [u8]> {
+        (len == self.0.len()).then_some(&mut *self.0)
+    }
+}
+
+// Allocates the buffer if the length is one of the parameter set lengths
+#[cfg(feature = "alloc")]
+struct VecSink<'a> {
+    lens: [usize; 3],
+    bytes: &'a mut Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl ByteSink for VecSink<'_> {
+    fn buffer(&mut self, len: usize) -> Option<&mut [u8]> {
+        if !self.lens.contains(&len) {
+            return None;
+        }
+        self.bytes.resize(len, 0);
+        Some(self.bytes.as_mut_slice())
+    }
+}
+
+struct BytesVisitor<'a, T: ByteSink> {
+    name: &'static str,
+    sink: &'a mut T,
+}
+
+impl<'de, T: ByteSink> Visitor<'de> for BytesVisitor<'_, T> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an ML-KEM {} as bytes or a base64 string", self.name)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<(), E> {
+        match self.sink.buffer(v.len()) {
+            Some(buf) => {
+                buf.copy_from_slice(v);
+                Ok(())
+            }
+            None => Err(E::invalid_length(v.len(), &self)),
+        }
+    }
+
+    // The errors never quote v, which holds the whole secret for decapsulation keys and seeds
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<(), E> {
+        // Padded base64 is a multiple of 4 characters with at most 2 padding characters
+        let padding = v.bytes().rev().take_while(|&b| b == b'=').count();
+        if !v.len().is_multiple_of(4) || padding > 2 {
+            return Err(E::invalid_value(Unexpected::Other("malformed base64"), &self));
+        }
+        let len = v.len() / 4 * 3 - padding;
+        let decoded = match self.sink.buffer(len) {
+            Some(buf) => Base64::decode(v, buf).map(|decoded| decoded.len()),
+            None => return Err(E::invalid_length(len, &self)),
+        };
+        match decoded {
+            Ok(decoded) if decoded == len => Ok(()),
+            _ => Err(E::invalid_value(Unexpected::Other("malformed base64"), &self)),
+        }
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>, T: ByteSink>(deserializer: D, name: &'static str, sink: &mut T) -> Result<(), D::Error> {
+    let visitor = BytesVisitor { name, sink };
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(visitor)
+    } else {
+        deserializer.deserialize_bytes(visitor)
+    }
+}
+
+impl<P: ParameterSet> Serialize for typed::EncapsKey<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.0.as_ref(), serializer)
+    }
+}
+
+impl<'de, P: ParameterSet> Deserialize<'de> for typed::EncapsKey<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut ek = P::EncapsKeyBytes::zeroed();
+        deserialize_bytes(deserializer, "encapsulation key", &mut FixedSink(ek.as_mut()))?;
+        typed::EncapsKey::from_bytes(ek).map_err(de::Error::custom)
+    }
+}
+
+impl<P: ParameterSet> Serialize for typed::DecapsKey<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.0.as_ref(), serializer)
+    }
+}
+
+// Decoded directly into the key, which zeroizes on drop if the checks fail
+impl<'de, P: ParameterSet> Deserialize<'de> for typed::DecapsKey<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut dk = typed::DecapsKey::<P>(P::DecapsKeyBytes::zeroed());
+        deserialize_bytes(deserializer, "decapsulation key", &mut FixedSink(dk.0.as_mut()))?;
+        if !check_decaps_key(P::K, dk.0.as_ref()) {
+            return Err(de::Error::custom(crate::Error::InvalidDecapsKey));
+        }
+        Ok(dk)
+    }
+}
+
+impl<P: ParameterSet> Serialize for typed::CipherText<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.0.as_ref(), serializer)
+    }
+}
+
+impl<'de, P: ParameterSet> Deserialize<'de> for typed::CipherText<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut c = P::CipherTextBytes::zeroed();
+        deserialize_bytes(deserializer, "ciphertext", &mut FixedSink(c.as_mut()))?;
+        Ok(typed::CipherText::from_bytes(c))
+    }
+}
+
+impl Serialize for DecapsKeySeed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DecapsKeySeed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut seed = DecapsKeySeed([0u8; 64]);
+        deserialize_bytes(deserializer, "decapsulation key seed", &mut FixedSink(&mut seed.0))?;
+        Ok(seed)
+    }
+}
+
+// The untyped values accept the lengths of any parameter set, checking the contents
+// where the length identifies the parameter set
+#[cfg(feature = "alloc")]
+const ENCAPS_KEY_LENS: [usize; 3] = [
+    <<MlKem512 as ParameterSet>::EncapsKeyBytes as ByteArray>::LEN,
+    <<MlKem768 as ParameterSet>::EncapsKeyBytes as ByteArray>::LEN,
+    <<MlKem1024 as ParameterSet>::EncapsKeyBytes as ByteArray>::LEN,
+];
+#[cfg(feature = "alloc")]
+const DECAPS_KEY_LENS: [usize; 3] = [
+    <<MlKem512 as ParameterSet>::DecapsKeyBytes as ByteArray>::LEN,
+    <<MlKem768 as ParameterSet>::DecapsKeyBytes as ByteArray>::LEN,
+    <<MlKem1024 as ParameterSet>::DecapsKeyBytes as ByteArray>::LEN,
+];
+#[cfg(feature = "alloc")]
+const CIPHERTEXT_LENS: [usize; 3] = [
+    <<MlKem512 as ParameterSet>::CipherTextBytes as ByteArray>::LEN,
+    <<MlKem768 as ParameterSet>::CipherTextBytes as ByteArray>::LEN,
+    <<MlKem1024 as ParameterSet>::CipherTextBytes as ByteArray>::LEN,
+];
+
+#[cfg(feature = "alloc")]
+impl Serialize for EncapsKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> Deserialize<'de> for EncapsKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut ek = EncapsKey(Vec::new());
+        deserialize_bytes(deserializer, "encapsulation key", &mut VecSink { lens: ENCAPS_KEY_LENS, bytes: &mut ek.0 })?;
+        // ek is 384k + 32 bytes
+        if !check_encaps_key((ek.0.len() - 32) / 384, &ek.0) {
+            return Err(de::Error::custom(crate::Error::InvalidEncapsKey));
+        }
+        Ok(ek)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for DecapsKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.0, serializer)
+    }
+}
+
+// Decoded directly into the key, which zeroizes on drop if the checks fail
+#[cfg(feature = "alloc")]
+impl<'de> Deserialize<'de> for DecapsKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut dk = DecapsKey(Vec::new());
+        deserialize_bytes(deserializer, "decapsulation key", &mut VecSink { lens: DECAPS_KEY_LENS, bytes: &mut dk.0 })?;
+        // dk is 768k + 96 bytes
+        if !check_decaps_key((dk.0.len() - 96) / 768, &dk.0) {
+            return Err(de::Error::custom(crate::Error::InvalidDecapsKey));
+        }
+        Ok(dk)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for CipherText {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> Deserialize<'de> for CipherText {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut c = CipherText(Vec::new());
+        deserialize_bytes(deserializer, "ciphertext", &mut VecSink { lens: CIPHERTEXT_LENS, bytes: &mut c.0 })?;
+        Ok(c)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{MlKemParams, typed::MlKem};
+    use std::{format, string::ToString, vec};
+
+    fn seed() -> DecapsKeySeed {
+        DecapsKeySeed::from_d_z(&[11u8; 32], &[22u8; 32])
+    }
+
+    #[test]
+    fn test_typed_json_roundtrip() {
+        let kem = MlKem::<MlKem768>::new();
+        let (ek, dk) = kem.keygen_from_seed(&seed());
+        let (ssk, ct) = kem.encaps_deterministic(&ek, &[33u8; 32]);
+
+        let ek_json = serde_json::to_string(&ek).unwrap();
+        let dk_json = serde_json::to_string(&dk).unwrap();
+        let ct_json = serde_json::to_string(&ct).unwrap();
+        // Quoted padded base64 of 1184 bytes
+        assert_eq!(ek_json.len(), 2 + 1184usize.div_ceil(3) * 4);
+        assert_eq!(ek_json.trim_matches('"'), Base64Display(ek.as_bytes()).to_string());
+
+        let ek_de: typed::EncapsKey<MlKem768> = serde_json::from_str(&ek_json).unwrap();
+        let dk_de: typed::DecapsKey<MlKem768> = serde_json::from_str(&dk_json).unwrap();
+        let ct_de: typed::CipherText<MlKem768> = serde_json::from_str(&ct_json).unwrap();
+        assert_eq!(ek_de.as_bytes(), ek.as_bytes());
+        assert_eq!(kem.decaps(&dk_de, &ct_de).into_bytes(), ssk.into_bytes());
+
+        // Wrong parameter set and invalid base64 are rejected
+        assert!(serde_json::from_str::<typed::EncapsKey<MlKem512>>(&ek_json).is_err());
+        assert!(serde_json::from_str::<typed::CipherText<MlKem768>>("\"not base64!\"").is_err());
+    }
+
+    #[test]
+    fn test_typed_bincode_roundtrip() {
+        let (ek, dk) = MlKem::<MlKem512>::new().keygen_from_seed(&seed());
+
+        let ek_bin = bincode::serialize(&ek).unwrap();
+        // Length prefix followed by the raw bytes
+        assert_eq!(&ek_bin[8..], ek.as_bytes());
+        let ek_de: typed::EncapsKey<MlKem512> = bincode::deserialize(&ek_bin).unwrap();
+        assert_eq!(ek_de.as_bytes(), ek.as_bytes());
+
+        let mut dk_bin = bincode::serialize(&dk).unwrap();
+        let dk_de: typed::DecapsKey<MlKem512> = bincode::deserialize(&dk_bin).unwrap();
+        assert_eq!(dk_de.as_bytes(), dk.as_bytes());
+
+        // Corrupting H(ek) fails the decapsulation key check
+        dk_bin[8 + 768 * 2 + 32] ^= 1;
+        assert!(bincode::deserialize::<typed::DecapsKey<MlKem512>>(&dk_bin).is_err());
+        assert!(bincode::deserialize::<typed::DecapsKey<MlKem512>>(&dk_bin[..dk_bin.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_encaps_key_modulus_check() {
+        let (ek, _) = MlKem::<MlKem1024>::new().keygen_from_seed(&seed());
+        let mut ek_bytes = ek.into_bytes();
+        // The first coefficient is set to q
+        ek_bytes[0] = 0x01;
+        ek_bytes[1] = (ek_bytes[1] & 0xF0) | 0x0D;
+        let json = format!("\"{}\"", Base64Display(&ek_bytes));
+
+        assert!(serde_json::from_str::<typed::EncapsKey<MlKem1024>>(&json).is_err());
+        assert!(serde_json::from_str::<EncapsKey>(&json).is_err());
+    }
+
+    #[test]
+    fn test_untyped_roundtrip() {
+        let kem = crate::MlKem::new(MlKemParams::MlKem1024);
+        let (ek, dk) = kem.keygen_from_seed(&seed());
+        let (ssk, ct) = kem.encaps_deterministic(&ek, &[44u8; 32]).unwrap();
+
+        let ek_de: EncapsKey = serde_json::from_str(&serde_json::to_string(&ek).unwrap()).unwrap();
+        let dk_de: DecapsKey = bincode::deserialize(&bincode::serialize(&dk).unwrap()).unwrap();
+        let ct_de: CipherText = serde_json::from_str(&serde_json::to_string(&ct).unwrap()).unwrap();
+
+        assert_eq!(ek_de.into_bytes(), ek.into_bytes());
+        assert_eq!(kem.decaps(&dk_de, &ct_de).unwrap().into_bytes(), ssk.into_bytes());
+
+        // Only the lengths of the parameter sets are accepted
+        let short = format!("\"{}\"", Base64Display(&[0u8; 1000]));
+        assert!(serde_json::from_str::<CipherText>(&short).is_err());
+        assert!(bincode::deserialize::<CipherText>(&bincode::serialize(&vec![0u8; 1000]).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_errors_do_not_echo_secrets() {
+        let (_, dk) = MlKem::<MlKem768>::new().keygen_from_seed(&seed());
+        let dk_base64 = Base64Display(dk.as_bytes()).to_string();
+        let seed_base64 = Base64Display(&seed().into_bytes()[..]).to_string();
+
+        for encoded in [&dk_base64, &seed_base64] {
+            // One character typo, and a dropped character that breaks the padding
+            let mut typo = encoded.clone();
+            typo.replace_range(10..11, "!");
+            let truncated = &encoded[1..];
+            for corrupted in [typo.as_str(), truncated] {
+                let json = format!("\"{corrupted}\"");
+                let errors = [
+                    serde_json::from_str::<typed::DecapsKey<MlKem768>>(&json).err().unwrap().to_string(),
+                    serde_json::from_str::<DecapsKey>(&json).err().unwrap().to_string(),
+                    serde_json::from_str::<DecapsKeySeed>(&json).err().unwrap().to_string(),
+                ];
+                for error in errors {
+                    assert!(!error.contains(&corrupted[12..]), "{error}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_seed_and_params() {
+        let seed_json = serde_json::to_string(&seed()).unwrap();
+        let seed_de: DecapsKeySeed = serde_json::from_str(&seed_json).unwrap();
+        assert_eq!(seed_de.into_bytes(), seed().into_bytes());
+
+        assert_eq!(serde_json::to_string(&MlKemParams::MlKem768).unwrap(), "\"ML-KEM-768\"");
+        assert_eq!(serde_json::from_str::<MlKemParams>("\"ML-KEM-1024\"").unwrap(), MlKemParams::MlKem1024);
+        let params_bin = bincode::serialize(&MlKemParams::MlKem512).unwrap();
+        assert_eq!(bincode::deserialize::<MlKemParams>(&params_bin).unwrap(), MlKemParams::MlKem512);
+    }
+}
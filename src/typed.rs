@@ -0,0 +1,398 @@
+use core::marker::PhantomData;
+
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+use rand_core::{TryCryptoRng, TryRngCore};
+
+use crate::{Error, ml_kem_internal::{PreparedDk, PreparedEk, check_decaps_key, check_encaps_key, ml_kem_decaps_internal, ml_kem_decaps_prepared, ml_kem_encaps_internal,
+ml_kem_encaps_prepared, ml_kem_keygen_internal},
+params::{ByteArray, ParameterSet}, types::{DecapsKeySeed, SharedSecretKey}};
+#[cfg(feature = "alloc")]
+use crate::types;
+
+/// An encapsulation key for the parameter set `P`, checked as required by FIPS 203 §7.2.
+#[derive(Clone)]
+pub struct EncapsKey<P: ParameterSet>(pub(crate) P::EncapsKeyBytes);
+
+/// A decapsulation key for the parameter set `P`, checked as required by FIPS 203 §7.3.
+#[derive(Clone)]
+pub struct DecapsKey<P: ParameterSet>(pub(crate) P::DecapsKeyBytes);
+
+/// A ciphertext for the parameter set `P`.
+#[derive(Clone)]
+pub struct CipherText<P: ParameterSet>(pub(crate) P::CipherTextBytes);
+
+impl<P: ParameterSet> EncapsKey<P> {
+    pub fn from_bytes(bytes: P::EncapsKeyBytes) -> Result<Self, Error> {
+        if !check_encaps_key(P::K, bytes.as_ref()) {
+            return Err(Error::InvalidEncapsKey);
+        }
+        Ok(EncapsKey(bytes))
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != P::EncapsKeyBytes::LEN {
+            return Err(Error::InvalidEncapsKey);
+        }
+        let mut ek = P::EncapsKeyBytes::zeroed();
+        ek.as_mut().copy_from_slice(bytes);
+        Self::from_bytes(ek)
+    }
+
+    pub fn as_bytes(&self) -> &P::EncapsKeyBytes { &self.0 }
+    pub fn into_bytes(self) -> P::EncapsKeyBytes { self.0 }
+}
+
+impl<P: ParameterSet> DecapsKey<P> {
+    pub fn from_bytes(bytes: P::DecapsKeyBytes) -> Result<Self, Error> {
+        if !check_decaps_key(P::K, bytes.as_ref()) {
+            return Err(Error::InvalidDecapsKey);
+        }
+        Ok(DecapsKey(bytes))
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != P::DecapsKeyBytes::LEN {
+            return Err(Error::InvalidDecapsKey);
+        }
+        let mut dk = P::DecapsKeyBytes::zeroed();
+        dk.as_mut().copy_from_slice(bytes);
+        Self::from_bytes(dk)
+    }
+
+    /// Returns the encapsulation key embedded in the decapsulation key.
+    pub fn encaps_key(&self) -> EncapsKey<P> {
+        let mut ek = P::EncapsKeyBytes::zeroed();
+        ek.as_mut().copy_from_slice(&self.0.as_ref()[384 * P::K .. 768 * P::K + 32]);
+        EncapsKey(ek)
+    }
+
+    pub fn as_bytes(&self) -> &P::DecapsKeyBytes { &self.0 }
+    pub fn into_bytes(self) -> Zeroizing<P::DecapsKeyBytes> { Zeroizing::new(self.0) }
+}
+
+impl<P: ParameterSet> CipherText<P> {
+    pub fn from_bytes(bytes: P::CipherTextBytes) -> Self {
+        CipherText(bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != P::CipherTextBytes::LEN {
+            return Err(Error::InvalidCipherText);
+        }
+        let mut c = P::CipherTextBytes::zeroed();
+        c.as_mut().copy_from_slice(bytes);
+        Ok(CipherText(c))
+    }
+
+    pub fn as_bytes(&self) -> &P::CipherTextBytes { &self.0 }
+    pub fn into_bytes(self) -> P::CipherTextBytes { self.0 }
+}
+
+/// An encapsulation key holding t and the matrix A in the NTT domain along with H(ek),
+/// see `MlKem::prepare_encaps_key`.
+#[derive(Clone)]
+pub struct PreparedEncapsKey<P: ParameterSet> {
+    ek: PreparedEk,
+    _params: PhantomData<P>,
+}
+
+/// A decapsulation key holding s, t and the matrix A in the NTT domain,
+/// see `MlKem::prepare_decaps_key`.
+#[derive(Clone)]
+pub struct PreparedDecapsKey<P: ParameterSet> {
+    dk: PreparedDk,
+    _params: PhantomData<P>,
+}
+
+// The parsed keys zeroize themselves on drop
+impl<P: ParameterSet> ZeroizeOnDrop for PreparedEncapsKey<P> {}
+impl<P: ParameterSet> ZeroizeOnDrop for PreparedDecapsKey<P> {}
+
+macro_rules! impl_zeroize_on_drop {
+    ($($name:ident),*) => {$(
+        impl<P: ParameterSet> Zeroize for $name<P> {
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        impl<P: ParameterSet> Drop for $name<P> {
+            fn drop(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        impl<P: ParameterSet> ZeroizeOnDrop for $name<P> {}
+    )*};
+}
+
+impl_zeroize_on_drop!(EncapsKey, DecapsKey, CipherText);
+
+// Conversions between the typed values and the untyped values used by crate::MlKem
+#[cfg(feature = "alloc")]
+impl<P: ParameterSet> From<EncapsKey<P>> for types::EncapsKey {
+    fn from(ek: EncapsKey<P>) -> Self {
+        types::EncapsKey::from_slice(ek.0.as_ref())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: ParameterSet> TryFrom<&types::EncapsKey> for EncapsKey<P> {
+    type Error = Error;
+
+    fn try_from(ek: &types::EncapsKey) -> Result<Self, Error> {
+        Self::from_slice(&ek.0)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: ParameterSet> From<DecapsKey<P>> for types::DecapsKey {
+    fn from(dk: DecapsKey<P>) -> Self {
+        types::DecapsKey::from_slice(dk.0.as_ref())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: ParameterSet> TryFrom<&types::DecapsKey> for DecapsKey<P> {
+    type Error = Error;
+
+    fn try_from(dk: &types::DecapsKey) -> Result<Self, Error> {
+        Self::from_slice(&dk.0)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: ParameterSet> From<CipherText<P>> for types::CipherText {
+    fn from(c: CipherText<P>) -> Self {
+        types::CipherText::from_slice(c.0.as_ref())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: ParameterSet> TryFrom<&types::CipherText> for CipherText<P> {
+    type Error = Error;
+
+    fn try_from(c: &types::CipherText) -> Result<Self, Error> {
+        Self::from_slice(&c.0)
+    }
+}
+
+/// ML-KEM for a parameter set fixed at compile time.
+///
+/// Keys and ciphertexts carry their parameter set, so passing an ML-KEM-512 key
+/// to `MlKem<MlKem1024>` does not compile.
+///
+/// ```compile_fail
+/// use fips203_rust::{MlKem512, MlKem1024, typed::MlKem};
+///
+/// let (ek, _) = MlKem::<MlKem512>::new().keygen().unwrap();
+/// let _ = MlKem::<MlKem1024>::new().encaps(&ek);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MlKem<P: ParameterSet>(PhantomData<P>);
+
+impl<P: ParameterSet> MlKem<P> {
+    pub fn new() -> Self {
+        MlKem(PhantomData)
+    }
+
+    pub fn keygen_with_rng<R: TryCryptoRng + TryRngCore>(&self, rng: &mut R) -> Result<(EncapsKey<P>, DecapsKey<P>), Error> {
+        let seed = DecapsKeySeed::generate_with_rng(rng)?;
+
+        Ok(self.keygen_from_seed(&seed))
+    }
+
+    // Deterministically expands the seed (d, z) into the key pair
+    pub fn keygen_from_seed(&self, seed: &DecapsKeySeed) -> (EncapsKey<P>, DecapsKey<P>) {
+        let mut ek = P::EncapsKeyBytes::zeroed();
+        let mut dk = P::DecapsKeyBytes::zeroed();
+        ml_kem_keygen_internal(P::K, P::ETA1, seed.d(), seed.z(), ek.as_mut(), dk.as_mut());
+
+        (EncapsKey(ek), DecapsKey(dk))
+    }
+
+    pub fn encaps_with_rng<R: TryCryptoRng + TryRngCore>(&self, ek: &EncapsKey<P>, rng: &mut R) -> Result<(SharedSecretKey, CipherText<P>), Error> {
+        let mut m = Zeroizing::new([0u8; 32]);
+        rng.try_fill_bytes(&mut m[..]).map_err(|_| Error::RandomnessFailure)?;
+
+        Ok(self.encaps_from_message(ek, &m))
+    }
+
+    /// ML-KEM.Encaps_internal from FIPS 203, with the message `m` supplied by the caller.
+    ///
+    /// Only for known-answer testing, `m` must otherwise be fresh randomness.
+    #[cfg(any(test, feature = "hazmat"))]
+    pub fn encaps_deterministic(&self, ek: &EncapsKey<P>, m: &[u8; 32]) -> (SharedSecretKey, CipherText<P>) {
+        self.encaps_from_message(ek, m)
+    }
+
+    pub(crate) fn encaps_from_message(&self, ek: &EncapsKey<P>, m: &[u8; 32]) -> (SharedSecretKey, CipherText<P>) {
+        let mut c = P::CipherTextBytes::zeroed();
+        let ssk = ml_kem_encaps_internal(P::K, P::ETA1, P::ETA2, P::DU, P::DV, ek.0.as_ref(), m, c.as_mut());
+
+        (ssk, CipherText(c))
+    }
+
+    // The key was checked on construction and the ciphertext length is fixed by the type
+    pub fn decaps(&self, dk: &DecapsKey<P>, c: &CipherText<P>) -> SharedSecretKey {
+        ml_kem_decaps_internal(P::K, P::ETA1, P::ETA2, P::DU, P::DV, dk.0.as_ref(), c.0.as_ref())
+    }
+
+    /// Parses the encapsulation key once, so that repeated encapsulations
+    /// skip decoding t and sampling the matrix A.
+    pub fn prepare_encaps_key(&self, ek: &EncapsKey<P>) -> PreparedEncapsKey<P> {
+        PreparedEncapsKey { ek: PreparedEk::new(P::K, ek.0.as_ref()), _params: PhantomData }
+    }
+
+    /// Parses the decapsulation key once, so that repeated decapsulations
+    /// skip decoding s and t and sampling the matrix A.
+    pub fn prepare_decaps_key(&self, dk: &DecapsKey<P>) -> PreparedDecapsKey<P> {
+        PreparedDecapsKey { dk: PreparedDk::new(P::K, dk.0.as_ref()), _params: PhantomData }
+    }
+
+    pub fn encaps_prepared_with_rng<R: TryCryptoRng + TryRngCore>(&self, ek: &PreparedEncapsKey<P>, rng: &mut R) -> Result<(SharedSecretKey, CipherText<P>), Error> {
+        let mut m = Zeroizing::new([0u8; 32]);
+        rng.try_fill_bytes(&mut m[..]).map_err(|_| Error::RandomnessFailure)?;
+
+        let mut c = P::CipherTextBytes::zeroed();
+        let ssk = ml_kem_encaps_prepared(P::K, P::ETA1, P::ETA2, P::DU, P::DV, &ek.ek, &m, c.as_mut());
+
+        Ok((ssk, CipherText(c)))
+    }
+
+    pub fn decaps_prepared(&self, dk: &PreparedDecapsKey<P>, c: &CipherText<P>) -> SharedSecretKey {
+        ml_kem_decaps_prepared(P::K, P::ETA1, P::ETA2, P::DU, P::DV, &dk.dk, c.0.as_ref())
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn keygen(&self) -> Result<(EncapsKey<P>, DecapsKey<P>), Error> {
+        self.keygen_with_rng(&mut OsRng)
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn encaps(&self, ek: &EncapsKey<P>) -> Result<(SharedSecretKey, CipherText<P>), Error> {
+        self.encaps_with_rng(ek, &mut OsRng)
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn encaps_prepared(&self, ek: &PreparedEncapsKey<P>) -> Result<(SharedSecretKey, CipherText<P>), Error> {
+        self.encaps_prepared_with_rng(ek, &mut OsRng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::{MlKem512, MlKem768, MlKem1024};
+    #[cfg(feature = "alloc")]
+    use crate::{MlKemParams, types};
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    fn roundtrip<P: ParameterSet>(seed: u64) {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let kem = MlKem::<P>::new();
+
+        let (ek, dk) = kem.keygen_with_rng(&mut rng).expect("random generation for key should not fail");
+        let (ssk_enc, ct) = kem.encaps_with_rng(&ek, &mut rng).expect("random generation for the encapsulation should not fail");
+        let ssk_dec = kem.decaps(&dk, &ct);
+
+        assert_eq!(ssk_enc.into_bytes(), ssk_dec.into_bytes());
+        assert_eq!(dk.encaps_key().into_bytes().as_ref(), ek.into_bytes().as_ref());
+    }
+
+    #[test]
+    fn test_typed_roundtrip() {
+        roundtrip::<MlKem512>(1);
+        roundtrip::<MlKem768>(2);
+        roundtrip::<MlKem1024>(3);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_typed_matches_untyped() {
+        let kem = MlKem::<MlKem768>::new();
+        let untyped_kem = crate::MlKem::new(MlKemParams::MlKem768);
+
+        let (ek, dk) = kem.keygen_with_rng(&mut ChaCha20Rng::seed_from_u64(7)).expect("random generation for key should not fail");
+        let (untyped_ek, untyped_dk) = untyped_kem.keygen_with_rng(&mut ChaCha20Rng::seed_from_u64(7)).expect("random generation for key should not fail");
+        assert_eq!(types::EncapsKey::from(ek.clone()).into_bytes(), untyped_ek.into_bytes());
+        assert_eq!(types::DecapsKey::from(dk.clone()).into_bytes(), untyped_dk.clone().into_bytes());
+
+        let (ssk_enc, ct) = kem.encaps_with_rng(&ek, &mut ChaCha20Rng::seed_from_u64(8)).expect("random generation for the encapsulation should not fail");
+        let ssk_dec = untyped_kem.decaps(&untyped_dk, &ct.into()).expect("decapsulation of a valid ciphertext should not fail");
+        assert_eq!(ssk_enc.into_bytes(), ssk_dec.into_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_typed_keygen_from_seed() {
+        let seed = DecapsKeySeed::from_d_z(&[1u8; 32], &[2u8; 32]);
+        let (ek, dk) = MlKem::<MlKem1024>::new().keygen_from_seed(&seed);
+        let (untyped_ek, untyped_dk) = crate::MlKem::new(MlKemParams::MlKem1024).keygen_from_seed(&seed);
+
+        assert_eq!(ek.into_bytes().as_ref(), untyped_ek.into_bytes().as_slice());
+        assert_eq!(dk.into_bytes().as_ref(), untyped_dk.into_bytes().as_slice());
+        assert_eq!(&seed.d()[..], &[1u8; 32]);
+        assert_eq!(&seed.z()[..], &[2u8; 32]);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_typed_encaps_deterministic() {
+        let seed = DecapsKeySeed::from([5u8; 64]);
+        let m = [6u8; 32];
+        let (ek, _) = MlKem::<MlKem512>::new().keygen_from_seed(&seed);
+        let (untyped_ek, _) = crate::MlKem::new(MlKemParams::MlKem512).keygen_from_seed(&seed);
+
+        let (ssk, ct) = MlKem::<MlKem512>::new().encaps_deterministic(&ek, &m);
+        let (untyped_ssk, untyped_ct) = crate::MlKem::new(MlKemParams::MlKem512).encaps_deterministic(&untyped_ek, &m).expect("encapsulation key should be valid");
+
+        assert_eq!(ssk.into_bytes(), untyped_ssk.into_bytes());
+        assert_eq!(ct.into_bytes().as_ref(), untyped_ct.into_bytes().as_slice());
+    }
+
+    #[test]
+    fn test_typed_prepared_keys() {
+        let kem = MlKem::<MlKem1024>::new();
+        let (ek, dk) = kem.keygen_from_seed(&DecapsKeySeed::from([3u8; 64]));
+        let prepared_ek = kem.prepare_encaps_key(&ek);
+        let prepared_dk = kem.prepare_decaps_key(&dk);
+
+        let (ssk, ct) = kem.encaps_prepared_with_rng(&prepared_ek, &mut ChaCha20Rng::seed_from_u64(10)).expect("random generation for the encapsulation should not fail");
+        let (expected_ssk, expected_ct) = kem.encaps_with_rng(&ek, &mut ChaCha20Rng::seed_from_u64(10)).expect("random generation for the encapsulation should not fail");
+        assert_eq!(ct.as_bytes().as_ref(), expected_ct.as_bytes().as_ref());
+        assert_eq!(ssk.clone().into_bytes(), expected_ssk.into_bytes());
+
+        assert_eq!(kem.decaps_prepared(&prepared_dk, &ct).into_bytes(), ssk.into_bytes());
+        // Implicit rejection gives the same result as the one-shot path
+        let mut bad_ct = ct.into_bytes();
+        bad_ct[0] ^= 1;
+        let bad_ct = CipherText::<MlKem1024>::from_bytes(bad_ct);
+        assert_eq!(kem.decaps_prepared(&prepared_dk, &bad_ct).into_bytes(), kem.decaps(&dk, &bad_ct).into_bytes());
+    }
+
+    #[test]
+    fn test_typed_from_slice_checks() {
+        let mut rng = ChaCha20Rng::seed_from_u64(9);
+        let (ek, dk) = MlKem::<MlKem512>::new().keygen_with_rng(&mut rng).expect("random generation for key should not fail");
+
+        // Wrong parameter set lengths are rejected
+        assert_eq!(EncapsKey::<MlKem768>::from_slice(ek.as_bytes()).err(), Some(Error::InvalidEncapsKey));
+        assert_eq!(DecapsKey::<MlKem1024>::from_slice(dk.as_bytes()).err(), Some(Error::InvalidDecapsKey));
+        assert_eq!(CipherText::<MlKem512>::from_slice(&[0u8; 1088]).err(), Some(Error::InvalidCipherText));
+
+        // Coefficients equal to q fail the modulus check
+        let mut ek_bytes = ek.into_bytes();
+        ek_bytes[0] = 0x01;
+        ek_bytes[1] = (ek_bytes[1] & 0xF0) | 0x0D;
+        assert_eq!(EncapsKey::<MlKem512>::from_bytes(ek_bytes).err(), Some(Error::InvalidEncapsKey));
+
+        let mut dk_bytes = dk.into_bytes();
+        dk_bytes[768 * 2 + 32] ^= 1;
+        assert_eq!(DecapsKey::<MlKem512>::from_bytes(*dk_bytes).err(), Some(Error::InvalidDecapsKey));
+    }
+}
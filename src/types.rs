@@ -1,15 +1,67 @@
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+use rand_core::{TryCryptoRng, TryRngCore};
+
+use crate::Error;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::ml_kem_internal::{PreparedDk, PreparedEk};
 
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct SharedSecretKey(pub(crate) [u8; 32]);
 
 impl SharedSecretKey {
-    pub fn into_bytes(self) -> [u8; 32] { self.0 }
+    pub fn into_bytes(self) -> Zeroizing<[u8; 32]> { Zeroizing::new(self.0) }
 }
 
+/// The 64-byte seed `d || z` from which ML-KEM.KeyGen_internal expands a key pair.
+///
+/// Storing the seed instead of the expanded `DecapsKey` takes 64 bytes for every parameter set.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct DecapsKeySeed(pub(crate) [u8; 64]);
+
+impl DecapsKeySeed {
+    pub fn generate_with_rng<R: TryCryptoRng + TryRngCore>(rng: &mut R) -> Result<Self, Error> {
+        let mut seed = DecapsKeySeed([0u8; 64]);
+        rng.try_fill_bytes(&mut seed.0[..32]).map_err(|_| Error::RandomnessFailure)?;
+        rng.try_fill_bytes(&mut seed.0[32..]).map_err(|_| Error::RandomnessFailure)?;
+        Ok(seed)
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn generate() -> Result<Self, Error> {
+        Self::generate_with_rng(&mut OsRng)
+    }
+
+    pub fn from_d_z(d: &[u8; 32], z: &[u8; 32]) -> Self {
+        let mut seed = DecapsKeySeed([0u8; 64]);
+        seed.0[..32].copy_from_slice(d);
+        seed.0[32..].copy_from_slice(z);
+        seed
+    }
+
+    pub(crate) fn d(&self) -> &[u8; 32] { self.0[..32].try_into().unwrap() }
+    pub(crate) fn z(&self) -> &[u8; 32] { self.0[32..].try_into().unwrap() }
+
+    pub fn into_bytes(self) -> Zeroizing<[u8; 64]> { Zeroizing::new(self.0) }
+}
+
+impl From<[u8; 64]> for DecapsKeySeed {
+    fn from(bytes: [u8; 64]) -> Self {
+        DecapsKeySeed(bytes)
+    }
+}
+
+#[cfg(feature = "alloc")]
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct EncapsKey(pub(crate) Vec<u8>);
 
+#[cfg(feature = "alloc")]
 impl EncapsKey {
     pub fn into_bytes(self) -> Vec<u8> { self.0.clone() }
     pub fn from_slice(bytes: &[u8]) -> Self {
@@ -17,31 +69,37 @@ impl EncapsKey {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl From<Vec<u8>> for EncapsKey {
     fn from(bytes: Vec<u8>) -> Self {
         EncapsKey(bytes)
     }
 }
 
+#[cfg(feature = "alloc")]
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct DecapsKey(pub(crate) Vec<u8>);
 
+#[cfg(feature = "alloc")]
 impl DecapsKey {
-    pub fn into_bytes(self) -> Vec<u8> { self.0.clone() }
+    pub fn into_bytes(mut self) -> Zeroizing<Vec<u8>> { Zeroizing::new(core::mem::take(&mut self.0)) }
     pub fn from_slice(bytes: &[u8]) -> Self {
         DecapsKey(bytes.to_vec())
     }
 }
 
+#[cfg(feature = "alloc")]
 impl From<Vec<u8>> for DecapsKey {
     fn from(bytes: Vec<u8>) -> Self {
         DecapsKey(bytes)
     }
 }
 
+#[cfg(feature = "alloc")]
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct CipherText(pub(crate) Vec<u8>);
 
+#[cfg(feature = "alloc")]
 impl CipherText {
     pub fn into_bytes(self) -> Vec<u8> { self.0.clone() }
     pub fn from_slice(bytes: &[u8]) -> Self {
@@ -49,8 +107,33 @@ impl CipherText {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl From<Vec<u8>> for CipherText {
     fn from(bytes: Vec<u8>) -> Self {
         CipherText(bytes)
     }
-}
\ No newline at end of file
+}
+
+/// An encapsulation key parsed by `MlKem::prepare_encaps_key`, holding t and the matrix A
+/// in the NTT domain along with H(ek).
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct PreparedEncapsKey {
+    pub(crate) k: usize,
+    pub(crate) ek: PreparedEk,
+}
+
+/// A decapsulation key parsed by `MlKem::prepare_decaps_key`, holding s, t and the matrix A
+/// in the NTT domain.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct PreparedDecapsKey {
+    pub(crate) k: usize,
+    pub(crate) dk: PreparedDk,
+}
+
+// The parsed keys zeroize themselves on drop
+#[cfg(feature = "alloc")]
+impl ZeroizeOnDrop for PreparedEncapsKey {}
+#[cfg(feature = "alloc")]
+impl ZeroizeOnDrop for PreparedDecapsKey {}
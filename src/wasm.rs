@@ -0,0 +1,154 @@
+//! JavaScript bindings generated by wasm-bindgen.
+//!
+//! Keys, ciphertexts and shared secrets cross the boundary as `Uint8Array`s. Secret inputs are
+//! copied into WASM memory that is zeroized after the call, and secret outputs live in the
+//! returned objects until `free()` zeroizes them. Copies handed to JavaScript are not cleared.
+
+use alloc::string::ToString;
+
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
+
+use crate::types::{CipherText, DecapsKey, DecapsKeySeed, EncapsKey, SharedSecretKey};
+
+/// An ML-KEM parameter set from FIPS 203 Table 2.
+#[wasm_bindgen(js_name = MlKemParams)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsMlKemParams {
+    MlKem512 = 512,
+    MlKem768 = 768,
+    MlKem1024 = 1024,
+}
+
+impl From<JsMlKemParams> for crate::MlKemParams {
+    fn from(params: JsMlKemParams) -> Self {
+        match params {
+            JsMlKemParams::MlKem512 => crate::MlKemParams::MlKem512,
+            JsMlKemParams::MlKem768 => crate::MlKemParams::MlKem768,
+            JsMlKemParams::MlKem1024 => crate::MlKemParams::MlKem1024,
+        }
+    }
+}
+
+fn js_error(error: crate::Error) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+// Copies the JavaScript bytes into WASM memory that is zeroized on drop
+fn secret_bytes(array: &Uint8Array) -> Zeroizing<alloc::vec::Vec<u8>> {
+    let mut bytes = Zeroizing::new(alloc::vec![0u8; array.length() as usize]);
+    array.copy_to(&mut bytes);
+    bytes
+}
+
+/// ML-KEM for one parameter set.
+#[wasm_bindgen(js_name = MlKem)]
+pub struct JsMlKem {
+    params: JsMlKemParams,
+    kem: crate::MlKem,
+}
+
+#[wasm_bindgen(js_class = MlKem)]
+impl JsMlKem {
+    #[wasm_bindgen(constructor)]
+    pub fn new(params: JsMlKemParams) -> JsMlKem {
+        JsMlKem { params, kem: crate::MlKem::new(params.into()) }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn params(&self) -> JsMlKemParams {
+        self.params
+    }
+
+    /// Generates a key pair with `crypto.getRandomValues`.
+    pub fn keygen(&self) -> Result<KeyPair, JsError> {
+        let (ek, dk) = self.kem.keygen().map_err(js_error)?;
+        Ok(KeyPair { ek, dk })
+    }
+
+    /// Deterministically expands the 64-byte seed `d || z` into a key pair.
+    #[wasm_bindgen(js_name = keygenFromSeed)]
+    pub fn keygen_from_seed(&self, seed: &Uint8Array) -> Result<KeyPair, JsError> {
+        if seed.length() != 64 {
+            return Err(JsError::new("seed must be 64 bytes"));
+        }
+        let mut d_z = DecapsKeySeed([0u8; 64]);
+        seed.copy_to(&mut d_z.0);
+        let (ek, dk) = self.kem.keygen_from_seed(&d_z);
+        Ok(KeyPair { ek, dk })
+    }
+
+    /// Encapsulates a fresh shared secret to the encapsulation key.
+    pub fn encaps(&self, ek: &[u8]) -> Result<Encapsulation, JsError> {
+        let (ssk, ct) = self.kem.encaps(&EncapsKey::from_slice(ek)).map_err(js_error)?;
+        Ok(Encapsulation { ssk, ct })
+    }
+
+    /// Decapsulates the shared secret from the ciphertext.
+    pub fn decaps(&self, dk: &Uint8Array, ciphertext: &[u8]) -> Result<SharedSecret, JsError> {
+        let dk = DecapsKey(core::mem::take(&mut *secret_bytes(dk)));
+        let ssk = self.kem.decaps(&dk, &CipherText::from_slice(ciphertext)).map_err(js_error)?;
+        Ok(SharedSecret(ssk))
+    }
+}
+
+/// A key pair, the decapsulation key is zeroized by `free()`.
+#[wasm_bindgen]
+#[derive(ZeroizeOnDrop)]
+pub struct KeyPair {
+    #[zeroize(skip)]
+    ek: EncapsKey,
+    dk: DecapsKey,
+}
+
+#[wasm_bindgen]
+impl KeyPair {
+    #[wasm_bindgen(getter, js_name = encapsKey)]
+    pub fn encaps_key(&self) -> Uint8Array {
+        Uint8Array::from(self.ek.0.as_slice())
+    }
+
+    /// A copy of the decapsulation key, which is not cleared by `free()`.
+    #[wasm_bindgen(getter, js_name = decapsKey)]
+    pub fn decaps_key(&self) -> Uint8Array {
+        Uint8Array::from(self.dk.0.as_slice())
+    }
+}
+
+/// A ciphertext and its shared secret, the shared secret is zeroized by `free()`.
+#[wasm_bindgen]
+#[derive(ZeroizeOnDrop)]
+pub struct Encapsulation {
+    ssk: SharedSecretKey,
+    #[zeroize(skip)]
+    ct: CipherText,
+}
+
+#[wasm_bindgen]
+impl Encapsulation {
+    #[wasm_bindgen(getter)]
+    pub fn ciphertext(&self) -> Uint8Array {
+        Uint8Array::from(self.ct.0.as_slice())
+    }
+
+    /// A copy of the shared secret, which is not cleared by `free()`.
+    #[wasm_bindgen(getter, js_name = sharedSecret)]
+    pub fn shared_secret(&self) -> Uint8Array {
+        Uint8Array::from(&self.ssk.0[..])
+    }
+}
+
+/// A decapsulated shared secret, zeroized by `free()`.
+#[wasm_bindgen]
+#[derive(ZeroizeOnDrop)]
+pub struct SharedSecret(SharedSecretKey);
+
+#[wasm_bindgen]
+impl SharedSecret {
+    /// A copy of the shared secret, which is not cleared by `free()`.
+    #[wasm_bindgen(getter)]
+    pub fn bytes(&self) -> Uint8Array {
+        Uint8Array::from(&self.0.0[..])
+    }
+}
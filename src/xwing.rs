@@ -0,0 +1,283 @@
+//! X-Wing, the hybrid of ML-KEM-768 and X25519 from draft-connolly-cfrg-xwing-kem.
+//!
+//! The shared secret stays secret as long as either ML-KEM-768 or X25519 is unbroken.
+
+use alloc::vec::Vec;
+
+use sha3::{Digest, Sha3_256, Shake256, digest::{ExtendableOutput, Update, XofReader}};
+use x25519_dalek::{X25519_BASEPOINT_BYTES, x25519};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+use rand_core::{TryCryptoRng, TryRngCore};
+
+use crate::{Error, MlKem, MlKemParams, ml_kem_internal::check_encaps_key, types::{self, DecapsKeySeed, SharedSecretKey}};
+
+/// Length of an X-Wing encapsulation key `pk_M || pk_X`.
+pub const ENCAPS_KEY_LEN: usize = 1216;
+/// Length of an X-Wing decapsulation key, the seed both component keys are expanded from.
+pub const DECAPS_KEY_LEN: usize = 32;
+/// Length of an X-Wing ciphertext `ct_M || ct_X`.
+pub const CIPHERTEXT_LEN: usize = 1120;
+
+// Lengths of the ML-KEM-768 encapsulation key and ciphertext at the start of the X-Wing encodings
+const ML_KEM_EK_LEN: usize = 1184;
+const ML_KEM_CT_LEN: usize = 1088;
+
+// "\.//^\" appended to the combiner input
+const XWING_LABEL: &[u8; 6] = b"\\.//^\\";
+
+/// An X-Wing encapsulation key, with the ML-KEM-768 part checked as required by FIPS 203 §7.2.
+#[derive(Clone)]
+pub struct EncapsKey([u8; ENCAPS_KEY_LEN]);
+
+/// An X-Wing decapsulation key, the 32-byte seed both component key pairs are expanded from.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct DecapsKey([u8; DECAPS_KEY_LEN]);
+
+/// An X-Wing ciphertext.
+#[derive(Clone)]
+pub struct CipherText([u8; CIPHERTEXT_LEN]);
+
+impl EncapsKey {
+    pub fn from_bytes(bytes: [u8; ENCAPS_KEY_LEN]) -> Result<Self, Error> {
+        if !check_encaps_key(3, &bytes[..ML_KEM_EK_LEN]) {
+            return Err(Error::InvalidEncapsKey);
+        }
+        Ok(EncapsKey(bytes))
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes(bytes.try_into().map_err(|_| Error::InvalidEncapsKey)?)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; ENCAPS_KEY_LEN] { &self.0 }
+    pub fn into_bytes(self) -> [u8; ENCAPS_KEY_LEN] { self.0 }
+
+    fn pk_x(&self) -> &[u8; 32] { self.0[ML_KEM_EK_LEN..].try_into().unwrap() }
+}
+
+impl DecapsKey {
+    // Every 32-byte string is a valid seed
+    pub fn from_bytes(bytes: [u8; DECAPS_KEY_LEN]) -> Self {
+        DecapsKey(bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(DecapsKey(bytes.try_into().map_err(|_| Error::InvalidDecapsKey)?))
+    }
+
+    /// Expands the seed and returns the matching encapsulation key.
+    pub fn encaps_key(&self) -> EncapsKey {
+        XWing::new().expand(self).2
+    }
+
+    pub fn as_bytes(&self) -> &[u8; DECAPS_KEY_LEN] { &self.0 }
+    pub fn into_bytes(self) -> Zeroizing<[u8; DECAPS_KEY_LEN]> { Zeroizing::new(self.0) }
+}
+
+impl CipherText {
+    pub fn from_bytes(bytes: [u8; CIPHERTEXT_LEN]) -> Self {
+        CipherText(bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(CipherText(bytes.try_into().map_err(|_| Error::InvalidCipherText)?))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; CIPHERTEXT_LEN] { &self.0 }
+    pub fn into_bytes(self) -> [u8; CIPHERTEXT_LEN] { self.0 }
+
+    fn ct_x(&self) -> &[u8; 32] { self.0[ML_KEM_CT_LEN..].try_into().unwrap() }
+}
+
+// SHA3-256(ss_M || ss_X || ct_X || pk_X || XWingLabel)
+fn combiner(ss_m: &SharedSecretKey, ss_x: &[u8; 32], ct_x: &[u8; 32], pk_x: &[u8; 32]) -> SharedSecretKey {
+    let mut hasher = Sha3_256::new();
+    Digest::update(&mut hasher, ss_m.0);
+    Digest::update(&mut hasher, ss_x);
+    Digest::update(&mut hasher, ct_x);
+    Digest::update(&mut hasher, pk_x);
+    Digest::update(&mut hasher, XWING_LABEL);
+    SharedSecretKey(hasher.finalize().into())
+}
+
+/// The X-Wing KEM, built on ML-KEM-768.
+pub struct XWing {
+    kem: MlKem
+}
+
+impl Default for XWing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl XWing {
+    pub fn new() -> Self {
+        XWing { kem: MlKem::new(MlKemParams::MlKem768) }
+    }
+
+    // Expands the seed with SHAKE256 into the ML-KEM-768 key pair and the X25519 private key
+    fn expand(&self, dk: &DecapsKey) -> (types::DecapsKey, Zeroizing<[u8; 32]>, EncapsKey) {
+        let mut expanded = Zeroizing::new([0u8; 96]);
+        let mut hasher = Shake256::default();
+        hasher.update(&dk.0);
+        hasher.finalize_xof().read(&mut *expanded);
+
+        let seed = DecapsKeySeed(expanded[..64].try_into().unwrap());
+        let (ek_m, dk_m) = self.kem.keygen_from_seed(&seed);
+        let sk_x = Zeroizing::new(expanded[64..].try_into().unwrap());
+        let pk_x = x25519(*sk_x, X25519_BASEPOINT_BYTES);
+
+        let mut ek = [0u8; ENCAPS_KEY_LEN];
+        ek[..ML_KEM_EK_LEN].copy_from_slice(&ek_m.0);
+        ek[ML_KEM_EK_LEN..].copy_from_slice(&pk_x);
+        (dk_m, sk_x, EncapsKey(ek))
+    }
+
+    pub fn keygen_with_rng<R: TryCryptoRng + TryRngCore>(&self, rng: &mut R) -> Result<(EncapsKey, DecapsKey), Error> {
+        let mut seed = DecapsKey([0u8; DECAPS_KEY_LEN]);
+        rng.try_fill_bytes(&mut seed.0).map_err(|_| Error::RandomnessFailure)?;
+
+        Ok(self.keygen_from_seed(&seed))
+    }
+
+    // Deterministically expands the seed into the key pair
+    pub fn keygen_from_seed(&self, seed: &DecapsKey) -> (EncapsKey, DecapsKey) {
+        let (_, _, ek) = self.expand(seed);
+
+        (ek, seed.clone())
+    }
+
+    pub fn encaps_with_rng<R: TryCryptoRng + TryRngCore>(&self, ek: &EncapsKey, rng: &mut R) -> Result<(SharedSecretKey, CipherText), Error> {
+        let mut eseed = Zeroizing::new([0u8; 64]);
+        rng.try_fill_bytes(&mut eseed[..]).map_err(|_| Error::RandomnessFailure)?;
+
+        Ok(self.encaps_from_seed(ek, &eseed))
+    }
+
+    /// X-Wing EncapsulateDerand, with the 64 bytes of randomness `eseed` supplied by the caller.
+    ///
+    /// Only for known-answer testing, `eseed` must otherwise be fresh randomness.
+    #[cfg(any(test, feature = "hazmat"))]
+    pub fn encaps_deterministic(&self, ek: &EncapsKey, eseed: &[u8; 64]) -> (SharedSecretKey, CipherText) {
+        self.encaps_from_seed(ek, eseed)
+    }
+
+    pub(crate) fn encaps_from_seed(&self, ek: &EncapsKey, eseed: &[u8; 64]) -> (SharedSecretKey, CipherText) {
+        let ek_x = Zeroizing::new(eseed[32..].try_into().unwrap());
+        let ct_x = x25519(*ek_x, X25519_BASEPOINT_BYTES);
+        let ss_x = Zeroizing::new(x25519(*ek_x, *ek.pk_x()));
+
+        let ek_m = types::EncapsKey(Vec::from(&ek.0[..ML_KEM_EK_LEN]));
+        let (ss_m, ct_m) = self.kem.encaps_from_message(&ek_m, eseed[..32].try_into().unwrap());
+
+        let mut ct = [0u8; CIPHERTEXT_LEN];
+        ct[..ML_KEM_CT_LEN].copy_from_slice(&ct_m.0);
+        ct[ML_KEM_CT_LEN..].copy_from_slice(&ct_x);
+        (combiner(&ss_m, &ss_x, &ct_x, ek.pk_x()), CipherText(ct))
+    }
+
+    // A tampered ciphertext decapsulates to an unrelated shared secret instead of failing
+    pub fn decaps(&self, dk: &DecapsKey, c: &CipherText) -> SharedSecretKey {
+        let (dk_m, sk_x, ek) = self.expand(dk);
+        let ss_m = self.kem.decaps_unchecked(&dk_m, &c.0[..ML_KEM_CT_LEN]);
+        let ss_x = Zeroizing::new(x25519(*sk_x, *c.ct_x()));
+
+        combiner(&ss_m, &ss_x, c.ct_x(), ek.pk_x())
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn keygen(&self) -> Result<(EncapsKey, DecapsKey), Error> {
+        self.keygen_with_rng(&mut OsRng)
+    }
+
+    #[cfg(feature = "default-rng")]
+    pub fn encaps(&self, ek: &EncapsKey) -> Result<(SharedSecretKey, CipherText), Error> {
+        self.encaps_with_rng(ek, &mut OsRng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // Checks vectors in the layout of the draft's test-vectors.json, fields seed, sk, pk, eseed, ct and ss
+    fn check_vectors(json: &str) {
+        let vectors: serde_json::Value = serde_json::from_str(json).unwrap();
+        let xwing = XWing::new();
+
+        for vector in vectors.as_array().unwrap() {
+            let field = |name: &str| decode_hex(vector[name].as_str().unwrap());
+            let seed = DecapsKey::from_slice(&field("seed")).unwrap();
+            let (ek, dk) = xwing.keygen_from_seed(&seed);
+            assert_eq!(dk.as_bytes()[..], field("sk")[..]);
+            assert_eq!(ek.as_bytes()[..], field("pk")[..]);
+            assert_eq!(seed.encaps_key().as_bytes()[..], field("pk")[..]);
+
+            let eseed: [u8; 64] = field("eseed").try_into().unwrap();
+            let (ssk_enc, ct) = xwing.encaps_deterministic(&ek, &eseed);
+            assert_eq!(ct.as_bytes()[..], field("ct")[..]);
+            assert_eq!(ssk_enc.0[..], field("ss")[..]);
+
+            let ssk_dec = xwing.decaps(&dk, &CipherText::from_slice(&field("ct")).unwrap());
+            assert_eq!(ssk_dec.0[..], field("ss")[..]);
+        }
+    }
+
+    // Computed independently with OpenSSL ML-KEM-768, X25519 and SHA3-256, these are not the draft's vectors
+    #[test]
+    fn test_vectors() {
+        check_vectors(include_str!("../tests/data/xwing_test_vectors.json"));
+    }
+
+    // The test-vectors.json published with https://datatracker.ietf.org/doc/draft-connolly-cfrg-xwing-kem/
+    // is not vendored yet, so this is reported as ignored instead of passing without checking anything
+    #[test]
+    #[ignore = "needs the draft's test-vectors.json in XWING_TEST_VECTORS"]
+    fn test_published_vectors() {
+        let path = std::env::var_os("XWING_TEST_VECTORS").expect("XWING_TEST_VECTORS should name the draft's test-vectors.json");
+        check_vectors(&std::fs::read_to_string(path).expect("XWING_TEST_VECTORS should name a readable file"));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(1216);
+        let xwing = XWing::new();
+
+        let (ek, dk) = xwing.keygen_with_rng(&mut rng).expect("random generation for key should not fail");
+        let (ssk_enc, ct) = xwing.encaps_with_rng(&ek, &mut rng).expect("random generation for the encapsulation should not fail");
+        let ssk_dec = xwing.decaps(&dk, &ct);
+        assert_eq!(ssk_enc.0, ssk_dec.0);
+
+        // Tampering with either component ciphertext changes the shared secret
+        for i in [0, ML_KEM_CT_LEN - 1, ML_KEM_CT_LEN, CIPHERTEXT_LEN - 1] {
+            let mut tampered = ct.clone();
+            tampered.0[i] ^= 1;
+            assert_ne!(xwing.decaps(&dk, &tampered).0, ssk_enc.0);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_encodings() {
+        let (ek, _) = XWing::new().keygen_with_rng(&mut ChaCha20Rng::seed_from_u64(1120)).unwrap();
+        let mut bytes = ek.into_bytes();
+        assert_eq!(EncapsKey::from_slice(&bytes[1..]).err(), Some(Error::InvalidEncapsKey));
+
+        // The first ML-KEM coefficient set to q fails the modulus check, the X25519 key is not checked
+        bytes[0] = 0x01;
+        bytes[1] = (bytes[1] & 0xF0) | 0x0D;
+        assert_eq!(EncapsKey::from_bytes(bytes).err(), Some(Error::InvalidEncapsKey));
+
+        assert_eq!(DecapsKey::from_slice(&[0u8; 64]).err(), Some(Error::InvalidDecapsKey));
+        assert_eq!(CipherText::from_slice(&[0u8; CIPHERTEXT_LEN + 1]).err(), Some(Error::InvalidCipherText));
+    }
+}
@@ -0,0 +1,122 @@
+/* Round-trips every parameter set through the C API of the shared library.
+ *
+ * Built and run by tests/c_api.rs, or by hand after `cargo build --features ffi`:
+ *   cc -Iinclude tests/c/test_fips203.c -Ltarget/debug -lfips203_rust -o test_fips203
+ *   LD_LIBRARY_PATH=target/debug ./test_fips203
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "fips203.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                          \
+    do {                                                                     \
+        if (!(cond)) {                                                       \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #cond);                                                  \
+            failures++;                                                      \
+        }                                                                    \
+    } while (0)
+
+#define CHECK_STATUS(call, expected)                                         \
+    do {                                                                     \
+        int status_ = (call);                                                \
+        if (status_ != (expected)) {                                         \
+            fprintf(stderr, "%s:%d: %s returned %d (%s)\n", __FILE__,        \
+                    __LINE__, #call, status_,                                \
+                    fips203_status_string(status_));                         \
+            failures++;                                                      \
+        }                                                                    \
+    } while (0)
+
+static void test_roundtrip(uint32_t params) {
+    size_t ek_len = fips203_encaps_key_len(params);
+    size_t dk_len = fips203_decaps_key_len(params);
+    size_t ct_len = fips203_ciphertext_len(params);
+    uint8_t *ek = malloc(ek_len);
+    uint8_t *dk = malloc(dk_len);
+    uint8_t *ct = malloc(ct_len);
+    uint8_t ss_enc[FIPS203_SHARED_SECRET_LEN];
+    uint8_t ss_dec[FIPS203_SHARED_SECRET_LEN];
+
+    CHECK(ek != NULL && dk != NULL && ct != NULL);
+    CHECK_STATUS(fips203_keygen(params, ek, ek_len, dk, dk_len), FIPS203_OK);
+    CHECK_STATUS(fips203_encaps(params, ek, ek_len, ct, ct_len, ss_enc), FIPS203_OK);
+    CHECK_STATUS(fips203_decaps(params, dk, dk_len, ct, ct_len, ss_dec), FIPS203_OK);
+    CHECK(memcmp(ss_enc, ss_dec, FIPS203_SHARED_SECRET_LEN) == 0);
+
+    /* A modified ciphertext decapsulates to the implicit rejection secret */
+    ct[0] ^= 1;
+    CHECK_STATUS(fips203_decaps(params, dk, dk_len, ct, ct_len, ss_dec), FIPS203_OK);
+    CHECK(memcmp(ss_enc, ss_dec, FIPS203_SHARED_SECRET_LEN) != 0);
+
+    free(ek);
+    free(dk);
+    free(ct);
+}
+
+static void test_seeded_keygen(uint32_t params) {
+    size_t ek_len = fips203_encaps_key_len(params);
+    size_t dk_len = fips203_decaps_key_len(params);
+    uint8_t seed[FIPS203_SEED_LEN];
+    uint8_t *ek1 = malloc(ek_len);
+    uint8_t *ek2 = malloc(ek_len);
+    uint8_t *dk1 = malloc(dk_len);
+    uint8_t *dk2 = malloc(dk_len);
+
+    for (size_t i = 0; i < FIPS203_SEED_LEN; i++) {
+        seed[i] = (uint8_t)i;
+    }
+    CHECK(ek1 != NULL && ek2 != NULL && dk1 != NULL && dk2 != NULL);
+    CHECK_STATUS(fips203_keygen_from_seed(params, seed, ek1, ek_len, dk1, dk_len), FIPS203_OK);
+    CHECK_STATUS(fips203_keygen_from_seed(params, seed, ek2, ek_len, dk2, dk_len), FIPS203_OK);
+    CHECK(memcmp(ek1, ek2, ek_len) == 0);
+    CHECK(memcmp(dk1, dk2, dk_len) == 0);
+
+    free(ek1);
+    free(ek2);
+    free(dk1);
+    free(dk2);
+}
+
+static void test_errors(void) {
+    uint8_t seed[FIPS203_SEED_LEN] = {0};
+    uint8_t ek[800];
+    uint8_t dk[1632];
+    uint8_t ct[768];
+    uint8_t ss[FIPS203_SHARED_SECRET_LEN];
+
+    CHECK(fips203_encaps_key_len(1) == 0);
+    CHECK_STATUS(fips203_keygen_from_seed(1, seed, ek, sizeof ek, dk, sizeof dk), FIPS203_ERR_INVALID_PARAMS);
+    CHECK_STATUS(fips203_keygen_from_seed(FIPS203_ML_KEM_512, NULL, ek, sizeof ek, dk, sizeof dk), FIPS203_ERR_NULL_POINTER);
+    CHECK_STATUS(fips203_keygen_from_seed(FIPS203_ML_KEM_512, seed, ek, sizeof ek - 1, dk, sizeof dk), FIPS203_ERR_BUFFER_LENGTH);
+    CHECK_STATUS(fips203_keygen_from_seed(FIPS203_ML_KEM_512, seed, ek, sizeof ek, dk, sizeof dk), FIPS203_OK);
+
+    CHECK_STATUS(fips203_encaps(FIPS203_ML_KEM_768, ek, sizeof ek, ct, sizeof ct, ss), FIPS203_ERR_BUFFER_LENGTH);
+    CHECK_STATUS(fips203_encaps(FIPS203_ML_KEM_512, ek, sizeof ek - 1, ct, sizeof ct, ss), FIPS203_ERR_INVALID_ENCAPS_KEY);
+    CHECK_STATUS(fips203_decaps(FIPS203_ML_KEM_512, dk, sizeof dk, ct, sizeof ct - 1, ss), FIPS203_ERR_INVALID_CIPHERTEXT);
+    dk[768 * 2 + 32] ^= 1;
+    CHECK_STATUS(fips203_decaps(FIPS203_ML_KEM_512, dk, sizeof dk, ct, sizeof ct, ss), FIPS203_ERR_INVALID_DECAPS_KEY);
+
+    CHECK(strcmp(fips203_status_string(FIPS203_ERR_RANDOMNESS), "random number generator failure") == 0);
+}
+
+int main(void) {
+    const uint32_t params[] = {FIPS203_ML_KEM_512, FIPS203_ML_KEM_768, FIPS203_ML_KEM_1024};
+
+    for (size_t i = 0; i < sizeof params / sizeof params[0]; i++) {
+        test_roundtrip(params[i]);
+        test_seeded_keygen(params[i]);
+    }
+    test_errors();
+
+    if (failures != 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return EXIT_FAILURE;
+    }
+    printf("all checks passed\n");
+    return EXIT_SUCCESS;
+}
@@ -0,0 +1,28 @@
+// Compiles tests/c/test_fips203.c against include/fips203.h and runs it linked to the cdylib
+#![cfg(all(feature = "ffi", unix))]
+
+use std::{env, path::PathBuf, process::Command};
+
+#[test]
+fn c_program_roundtrip() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // The cdylib is built next to the test binary in target/<profile>/deps
+    let lib_dir = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    let exe = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("test_fips203");
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".into());
+
+    let status = Command::new(cc)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror"])
+        .arg("-I").arg(root.join("include"))
+        .arg(root.join("tests/c/test_fips203.c"))
+        .arg("-L").arg(&lib_dir)
+        .arg("-lfips203_rust")
+        .arg("-o").arg(&exe)
+        .status()
+        .expect("a C compiler is needed to build the C API test");
+    assert!(status.success(), "compiling the C test program failed");
+
+    // Cargo puts target/<profile> on the library path as well, which may hold a build without the C API
+    let output = Command::new(&exe).env("LD_LIBRARY_PATH", &lib_dir).env("DYLD_LIBRARY_PATH", &lib_dir).output().unwrap();
+    assert!(output.status.success(), "C test program failed:\n{}", String::from_utf8_lossy(&output.stderr));
+}
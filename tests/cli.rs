@@ -0,0 +1,85 @@
+// Runs the fips203 binary end to end in a scratch directory
+#![cfg(feature = "cli")]
+
+use std::{fs, path::{Path, PathBuf}, process::{Command, Output}};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn fips203(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fips203")).current_dir(dir).args(args).output().unwrap()
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = fips203(dir, args);
+    assert!(output.status.success(), "fips203 {args:?} failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn roundtrip_every_format() {
+    for (params, format) in [("512", "pem"), ("768", "hex"), ("1024", "base64"), ("ML-KEM-768", "raw")] {
+        let dir = scratch_dir(&format!("cli-{params}-{format}"));
+        run(&dir, &["keygen", "-p", params, "-f", format, "--ek-out", "ek", "--dk-out", "dk"]);
+        let data_format = if format == "pem" { "hex" } else { format };
+        run(&dir, &["encaps", "--ek", "ek", "--ct-out", "ct", "--ss-out", "ss1", "-f", data_format]);
+        run(&dir, &["decaps", "--dk", "dk", "--ct", "ct", "--ss-out", "ss2", "-f", data_format]);
+        assert_eq!(fs::read(dir.join("ss1")).unwrap(), fs::read(dir.join("ss2")).unwrap());
+
+        run(&dir, &["pubkey", "--dk", "dk", "--ek-out", "ek2", "-f", format]);
+        assert_eq!(fs::read(dir.join("ek")).unwrap(), fs::read(dir.join("ek2")).unwrap());
+    }
+}
+
+#[test]
+fn seeded_keygen_matches_openssl() {
+    let dir = scratch_dir("cli-openssl");
+    let seed: String = (0..64u8).map(|b| format!("{b:02x}")).collect();
+    fs::write(dir.join("seed"), seed).unwrap();
+
+    run(&dir, &["keygen", "-p", "512", "--seed", "seed", "--ek-out", "ek.pem", "--dk-out", "dk.pem", "--dk-contents", "both"]);
+    assert_eq!(fs::read_to_string(dir.join("ek.pem")).unwrap(), include_str!("data/ml_kem_512_pub.pem"));
+    assert_eq!(fs::read_to_string(dir.join("dk.pem")).unwrap(), include_str!("data/ml_kem_512_both.pem"));
+}
+
+#[test]
+fn inspect_reports_checks() {
+    let dir = scratch_dir("cli-inspect");
+    run(&dir, &["keygen", "-p", "768", "-f", "hex", "--ek-out", "ek", "--dk-out", "dk"]);
+
+    let report = run(&dir, &["inspect", "dk"]);
+    assert!(report.contains("type: decapsulation key"));
+    assert!(report.contains("parameter set: ML-KEM-768"));
+    assert!(report.contains("check: ok"));
+
+    // Flipping a bit of H(ek) fails the FIPS 203 §7.3 hash check
+    let mut dk = fs::read_to_string(dir.join("dk")).unwrap();
+    let h = 2 * (768 * 3 + 32);
+    let flipped = if &dk[h..h + 1] == "0" { "1" } else { "0" };
+    dk.replace_range(h..h + 1, flipped);
+    fs::write(dir.join("dk"), dk).unwrap();
+    assert!(run(&dir, &["inspect", "dk"]).contains("check: failed, invalid decapsulation key"));
+    assert!(!fips203(&dir, &["pubkey", "--dk", "dk", "--ek-out", "ek2"]).status.success());
+
+    let seed_pem = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/ml_kem_512_seed.pem");
+    let report = run(&dir, &["inspect", seed_pem.to_str().unwrap()]);
+    assert!(report.contains("PKCS#8 PEM with seed"));
+    assert!(report.contains("parameter set: ML-KEM-512"));
+}
+
+#[test]
+fn rejects_bad_input() {
+    let dir = scratch_dir("cli-errors");
+    fs::write(dir.join("short"), [0u8; 100]).unwrap();
+    assert!(!fips203(&dir, &["encaps", "--ek", "short", "--ct-out", "ct", "--ss-out", "ss"]).status.success());
+    assert!(!fips203(&dir, &["keygen", "-p", "999", "--ek-out", "ek", "--dk-out", "dk"]).status.success());
+
+    run(&dir, &["keygen", "--ek-out", "ek.pem", "--dk-out", "dk.pem"]);
+    let output = fips203(&dir, &["encaps", "--ek", "ek.pem", "--ct-out", "ct", "--ss-out", "ss", "-f", "pem"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("PEM is only defined for keys"));
+}
@@ -0,0 +1,78 @@
+// Runs in Node with: cargo test --target wasm32-unknown-unknown --features wasm --test wasm
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use core::{mem::MaybeUninit, slice};
+
+use fips203_rust::{MlKem, types::DecapsKeySeed, wasm::{JsMlKem, JsMlKemParams}};
+use js_sys::Uint8Array;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const ALL_PARAMS: [JsMlKemParams; 3] = [JsMlKemParams::MlKem512, JsMlKemParams::MlKem768, JsMlKemParams::MlKem1024];
+
+#[wasm_bindgen_test]
+fn roundtrip() {
+    for params in ALL_PARAMS {
+        let kem = JsMlKem::new(params);
+        let keys = kem.keygen().unwrap();
+        let enc = kem.encaps(&keys.encaps_key().to_vec()).unwrap();
+        let ssk = kem.decaps(&keys.decaps_key(), &enc.ciphertext().to_vec()).unwrap();
+
+        assert_eq!(enc.shared_secret().to_vec(), ssk.bytes().to_vec());
+        assert_eq!(ssk.bytes().length(), 32);
+    }
+}
+
+#[wasm_bindgen_test]
+fn keygen_from_seed_matches_rust() {
+    let seed = [9u8; 64];
+    let kem = JsMlKem::new(JsMlKemParams::MlKem768);
+    let keys = kem.keygen_from_seed(&Uint8Array::from(&seed[..])).unwrap();
+
+    let (ek, dk) = MlKem::new(kem.params().into()).keygen_from_seed(&DecapsKeySeed::from(seed));
+    assert_eq!(keys.encaps_key().to_vec(), ek.into_bytes());
+    assert_eq!(keys.decaps_key().to_vec(), *dk.into_bytes());
+
+    assert!(kem.keygen_from_seed(&Uint8Array::from(&seed[..32])).is_err());
+}
+
+#[wasm_bindgen_test]
+fn rejects_invalid_inputs() {
+    let kem = JsMlKem::new(JsMlKemParams::MlKem512);
+    let keys = kem.keygen().unwrap();
+    let ek = keys.encaps_key().to_vec();
+    let ct = kem.encaps(&ek).unwrap().ciphertext().to_vec();
+
+    // Keys and ciphertexts of another parameter set
+    let other = JsMlKem::new(JsMlKemParams::MlKem768);
+    assert!(other.encaps(&ek).is_err());
+    assert!(other.decaps(&keys.decaps_key(), &ct).is_err());
+    assert!(kem.decaps(&keys.decaps_key(), &ct[1..]).is_err());
+
+    // Corrupting H(ek) fails the decapsulation key check
+    let dk = keys.decaps_key();
+    dk.set_index(768 * 2 + 32, dk.get_index(768 * 2 + 32) ^ 1);
+    assert!(kem.decaps(&dk, &ct).is_err());
+}
+
+// free() drops the Rust value. Dropping it in place instead of freeing it keeps its memory readable,
+// so the test can check that no copy of the shared secret is left there.
+fn assert_wiped_on_drop<T>(value: T, secret: &[u8]) {
+    let mut slot = MaybeUninit::new(value);
+    // SAFETY: slot holds an initialized value that is dropped once and never used afterwards
+    unsafe { slot.assume_init_drop() };
+    // SAFETY: the memory of slot is still allocated, and the bytes left by the drop are read as u8
+    let bytes = unsafe { slice::from_raw_parts(slot.as_ptr().cast::<u8>(), size_of::<T>()) };
+    assert!(!bytes.windows(secret.len()).any(|window| window == secret));
+}
+
+#[wasm_bindgen_test]
+fn secrets_wiped_on_free() {
+    let kem = JsMlKem::new(JsMlKemParams::MlKem768);
+    let keys = kem.keygen().unwrap();
+    let enc = kem.encaps(&keys.encaps_key().to_vec()).unwrap();
+    let ssk = kem.decaps(&keys.decaps_key(), &enc.ciphertext().to_vec()).unwrap();
+    let secret = ssk.bytes().to_vec();
+
+    assert_wiped_on_drop(enc, &secret);
+    assert_wiped_on_drop(ssk, &secret);
+}
@@ -0,0 +1,114 @@
+// Runs the Wycheproof-format vectors in tests/data/wycheproof through the runtime and typed APIs
+#![cfg(feature = "alloc")]
+
+use fips203_rust::{MlKem, MlKem512, MlKem768, MlKem1024, ParameterSet, typed,
+types::{CipherText, DecapsKey, DecapsKeySeed, EncapsKey}};
+use serde_json::Value;
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    assert!(hex.len().is_multiple_of(2), "odd length hex string");
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("invalid hex string")).collect()
+}
+
+fn bytes(value: &Value, name: &str) -> Vec<u8> {
+    decode_hex(value[name].as_str().unwrap_or_else(|| panic!("missing {name}")))
+}
+
+// The outcome of one API call, Ok holds the shared secret and for encapsulation the ciphertext
+type Outcome = Result<(Vec<u8>, Vec<u8>), fips203_rust::Error>;
+
+// Checks an outcome against the result label and the expected ciphertext and shared secret
+fn check(api: &str, test: &Value, outcome: Outcome) -> Result<(), String> {
+    let id = format!("tcId {} ({}) with the {api} API", test["tcId"], test["comment"].as_str().unwrap_or(""));
+    let expected_k = bytes(test, "K");
+    let expected_c = test.get("m").map(|_| bytes(test, "c"));
+    match (test["result"].as_str(), outcome) {
+        (Some("invalid"), Err(_)) | (Some("acceptable"), Err(_)) => Ok(()),
+        (Some("invalid"), Ok(_)) => Err(format!("{id}: accepted an invalid input")),
+        (Some("valid"), Err(e)) => Err(format!("{id}: rejected a valid input with {e}")),
+        (Some("valid" | "acceptable"), Ok((k, c))) => {
+            if expected_c.as_ref().is_some_and(|expected| !expected.is_empty() && *expected != c) {
+                Err(format!("{id}: ciphertext mismatch"))
+            } else if !expected_k.is_empty() && expected_k != k {
+                Err(format!("{id}: shared secret mismatch"))
+            } else {
+                Ok(())
+            }
+        }
+        (result, _) => Err(format!("{id}: unknown result {result:?}"))
+    }
+}
+
+fn run_test<P: ParameterSet>(group: &Value, test: &Value) -> Vec<String> {
+    let kem = MlKem::new(P::PARAMS);
+    let typed_kem = typed::MlKem::<P>::new();
+    let (untyped, typed) = match group["type"].as_str() {
+        Some("MLKEMDecapsTest" | "MLKEMDecapsValidationTest") => {
+            let dk = test.get("dk").map_or_else(|| bytes(group, "dk"), |_| bytes(test, "dk"));
+            let c = bytes(test, "c");
+            let untyped = kem.decaps(&DecapsKey::from_slice(&dk), &CipherText::from_slice(&c)).map(|k| (k.into_bytes().to_vec(), c.clone()));
+            // The typed API goes through a prepared key, covering decaps_prepared as well
+            let typed = typed::DecapsKey::<P>::from_slice(&dk).and_then(|dk| {
+                let c = typed::CipherText::<P>::from_slice(&c)?;
+                let dk = typed_kem.prepare_decaps_key(&dk);
+                Ok((typed_kem.decaps_prepared(&dk, &c).into_bytes().to_vec(), c.as_bytes().as_ref().to_vec()))
+            });
+            (untyped, typed)
+        }
+        Some("MLKEMEncapsTest") => {
+            let ek = bytes(test, "ek");
+            let m: [u8; 32] = bytes(test, "m").try_into().expect("m is not 32 bytes");
+            let untyped = kem.encaps_deterministic(&EncapsKey::from_slice(&ek), &m).map(|(k, c)| (k.into_bytes().to_vec(), c.into_bytes()));
+            let typed = typed::EncapsKey::<P>::from_slice(&ek).map(|ek| {
+                let (k, c) = typed_kem.encaps_deterministic(&ek, &m);
+                (k.into_bytes().to_vec(), c.as_bytes().as_ref().to_vec())
+            });
+            (untyped, typed)
+        }
+        other => return vec![format!("tcId {}: unknown group type {other:?}", test["tcId"])]
+    };
+    [check("runtime", test, untyped), check("typed", test, typed)].into_iter().filter_map(Result::err).collect()
+}
+
+fn run_file<P: ParameterSet>(file: &str) {
+    let path = format!("{}/tests/data/wycheproof/{file}", env!("CARGO_MANIFEST_DIR"));
+    let doc: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let kem = MlKem::new(P::PARAMS);
+
+    let mut failures = Vec::new();
+    let mut total = 0;
+    for group in doc["testGroups"].as_array().unwrap() {
+        assert_eq!(group["parameterSet"], format!("{:?}", P::PARAMS).replace("MlKem", "ML-KEM-"));
+
+        // The decapsulation key of a group is derived from its seed
+        if let Some(seed) = group.get("seed") {
+            let seed: [u8; 64] = decode_hex(seed.as_str().unwrap()).try_into().unwrap();
+            let (ek, dk) = kem.keygen_from_seed(&DecapsKeySeed::from(seed));
+            assert_eq!(ek.into_bytes(), bytes(group, "ek"), "{file}: ek does not match the group seed");
+            assert_eq!(*dk.into_bytes(), bytes(group, "dk"), "{file}: dk does not match the group seed");
+        }
+
+        for test in group["tests"].as_array().unwrap() {
+            total += 1;
+            failures.extend(run_test::<P>(group, test));
+        }
+    }
+
+    assert_eq!(Some(total), doc["numberOfTests"].as_u64(), "{file}: numberOfTests does not match the test count");
+    assert!(failures.is_empty(), "{} of {total} tests in {file} failed:\n{}", failures.len(), failures.join("\n"));
+}
+
+#[test]
+fn wycheproof_ml_kem_512() {
+    run_file::<MlKem512>("mlkem_512_test.json");
+}
+
+#[test]
+fn wycheproof_ml_kem_768() {
+    run_file::<MlKem768>("mlkem_768_test.json");
+}
+
+#[test]
+fn wycheproof_ml_kem_1024() {
+    run_file::<MlKem1024>("mlkem_1024_test.json");
+}